@@ -0,0 +1,272 @@
+use macroquad::prelude::*;
+
+use crate::collision::collision_class::circle_rect_contact;
+
+// Thickness of the invisible walls just outside solid edges
+const WALL_THICKNESS: f32 = 1000.0;
+// Space kept between the paddle and the arena's left, right and bottom edges
+const PADDLE_MARGIN: f32 = 10.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Left,
+    Right,
+    Top,
+    Bottom,
+}
+
+impl Side {
+    pub const ALL: [Side; 4] = [Side::Left, Side::Right, Side::Top, Side::Bottom];
+
+    pub fn opposite(self) -> Side {
+        match self {
+            Side::Left => Side::Right,
+            Side::Right => Side::Left,
+            Side::Top => Side::Bottom,
+            Side::Bottom => Side::Top,
+        }
+    }
+}
+
+// What happens to a ball at one edge of the arena
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Edge {
+    // The ball bounces off it
+    Solid,
+    // The ball passes through and comes back in at the opposite edge
+    Open,
+    // A ball that gets past it is lost
+    Kill,
+}
+
+// The play area: its rectangle, what each edge does and any solid obstacles inside it.
+// Balls, the paddle, the tiles and everything else that moves ask it where the
+// boundaries are instead of using the screen
+#[derive(Debug, Clone, PartialEq)]
+pub struct Arena {
+    pub bounds: Rect,
+    pub left: Edge,
+    pub right: Edge,
+    pub top: Edge,
+    pub bottom: Edge,
+    // Solid blocks inside the arena that balls bounce off
+    pub obstacles: Vec<Rect>,
+    pub paddle_margin: f32,
+}
+
+impl Arena {
+    // Classic breakout: solid left, right and top edges, the ball is lost off the bottom
+    pub fn new(bounds: Rect) -> Self {
+        Arena {
+            bounds,
+            left: Edge::Solid,
+            right: Edge::Solid,
+            top: Edge::Solid,
+            bottom: Edge::Kill,
+            obstacles: vec![],
+            paddle_margin: PADDLE_MARGIN,
+        }
+    }
+
+    pub fn edge(&self, side: Side) -> Edge {
+        match side {
+            Side::Left => self.left,
+            Side::Right => self.right,
+            Side::Top => self.top,
+            Side::Bottom => self.bottom,
+        }
+    }
+
+    // Everything a ball bounces off: a thick wall behind every solid edge, plus the obstacles
+    pub fn walls(&self) -> Vec<Rect> {
+        let bounds = self.bounds;
+        let tall = bounds.h + WALL_THICKNESS * 2.0;
+
+        let mut walls: Vec<Rect> = Side::ALL
+            .into_iter()
+            .filter(|&side| self.edge(side) == Edge::Solid)
+            .map(|side| match side {
+                Side::Left => Rect::new(
+                    bounds.left() - WALL_THICKNESS,
+                    bounds.top() - WALL_THICKNESS,
+                    WALL_THICKNESS,
+                    tall,
+                ),
+                Side::Right => Rect::new(
+                    bounds.right(),
+                    bounds.top() - WALL_THICKNESS,
+                    WALL_THICKNESS,
+                    tall,
+                ),
+                Side::Top => Rect::new(
+                    bounds.left(),
+                    bounds.top() - WALL_THICKNESS,
+                    bounds.w,
+                    WALL_THICKNESS,
+                ),
+                Side::Bottom => Rect::new(bounds.left(), bounds.bottom(), bounds.w, WALL_THICKNESS),
+            })
+            .collect();
+        walls.extend(self.obstacles.iter().copied());
+        walls
+    }
+
+    // Whether a circle has gone completely past `side`
+    fn is_past(&self, side: Side, position: Vec2, radius: f32) -> bool {
+        match side {
+            Side::Left => position.x + radius < self.bounds.left(),
+            Side::Right => position.x - radius > self.bounds.right(),
+            Side::Top => position.y + radius < self.bounds.top(),
+            Side::Bottom => position.y - radius > self.bounds.bottom(),
+        }
+    }
+
+    // True once a ball is past a kill edge
+    pub fn is_lost(&self, position: Vec2, radius: f32) -> bool {
+        Side::ALL
+            .into_iter()
+            .any(|side| self.edge(side) == Edge::Kill && self.is_past(side, position, radius))
+    }
+
+    // Where a ball that went out through an open edge comes back in: just outside the
+    // opposite edge, still moving the same way. None while it hasn't left
+    pub fn wrap(&self, position: Vec2, radius: f32) -> Option<Vec2> {
+        let side = Side::ALL
+            .into_iter()
+            .find(|&side| self.edge(side) == Edge::Open && self.is_past(side, position, radius))?;
+
+        let bounds = self.bounds;
+        let wrapped = match side.opposite() {
+            Side::Left => Vec2::new(bounds.left() - radius, position.y),
+            Side::Right => Vec2::new(bounds.right() + radius, position.y),
+            Side::Top => Vec2::new(position.x, bounds.top() - radius),
+            Side::Bottom => Vec2::new(position.x, bounds.bottom() + radius),
+        };
+        Some(wrapped)
+    }
+
+    // Whether a ball can be placed here: entirely inside the bounds and not touching
+    // any obstacle
+    pub fn has_room_for(&self, position: Vec2, radius: f32) -> bool {
+        let bounds = self.bounds;
+        let inside = position.x - radius >= bounds.left()
+            && position.x + radius <= bounds.right()
+            && position.y - radius >= bounds.top()
+            && position.y + radius <= bounds.bottom();
+
+        inside
+            && self
+                .obstacles
+                .iter()
+                .all(|&obstacle| circle_rect_contact(position, radius, obstacle).is_none())
+    }
+
+    // Range the paddle's left edge may move in for a paddle of `width`
+    pub fn paddle_range(&self, width: f32) -> (f32, f32) {
+        let min_x = self.bounds.left() + self.paddle_margin;
+        let max_x = (self.bounds.right() - self.paddle_margin - width).max(min_x);
+        (min_x, max_x)
+    }
+
+    // Top-left corner of a paddle standing centred on the arena's floor
+    pub fn paddle_start(&self, width: f32, height: f32) -> Vec2 {
+        Vec2::new(
+            self.bounds.center().x - width / 2.0,
+            self.bounds.bottom() - height - self.paddle_margin,
+        )
+    }
+
+    // Whether something (a capsule, a projectile) has left the arena for good
+    pub fn is_outside(&self, rect: Rect) -> bool {
+        !rect.overlaps(&self.bounds)
+    }
+
+    pub fn draw(&self) {
+        for obstacle in &self.obstacles {
+            draw_rectangle(obstacle.x, obstacle.y, obstacle.w, obstacle.h, DARKGRAY);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RADIUS: f32 = 10.0;
+
+    fn arena() -> Arena {
+        Arena::new(Rect::new(100.0, 50.0, 800.0, 600.0))
+    }
+
+    #[test]
+    fn classic_arena_walls_solid_sides_and_kills_off_the_bottom() {
+        let arena = arena();
+        assert_eq!(arena.walls().len(), 3);
+
+        // Touching or only partly past the bottom edge isn't a miss yet
+        assert!(!arena.is_lost(Vec2::new(500.0, 650.0), RADIUS));
+        assert!(!arena.is_lost(Vec2::new(500.0, 659.0), RADIUS));
+        assert!(arena.is_lost(Vec2::new(500.0, 661.0), RADIUS));
+        // Solid sides never lose the ball, and never wrap it
+        assert!(!arena.is_lost(Vec2::new(50.0, 300.0), RADIUS));
+        assert_eq!(arena.wrap(Vec2::new(50.0, 300.0), RADIUS), None);
+    }
+
+    #[test]
+    fn open_sides_wrap_to_the_opposite_edge() {
+        let arena = Arena {
+            left: Edge::Open,
+            right: Edge::Open,
+            ..arena()
+        };
+        assert_eq!(arena.walls().len(), 1);
+
+        // Fully past the right edge comes back in just outside the left one
+        let wrapped = arena.wrap(Vec2::new(911.0, 300.0), RADIUS);
+        assert_eq!(wrapped, Some(Vec2::new(90.0, 300.0)));
+        let wrapped = arena.wrap(Vec2::new(89.0, 300.0), RADIUS);
+        assert_eq!(wrapped, Some(Vec2::new(910.0, 300.0)));
+        // Still overlapping the edge, not wrapped yet
+        assert_eq!(arena.wrap(Vec2::new(905.0, 300.0), RADIUS), None);
+        assert!(!arena.is_lost(Vec2::new(911.0, 300.0), RADIUS));
+    }
+
+    #[test]
+    fn open_top_wraps_to_the_bottom_without_losing_the_ball() {
+        let arena = Arena {
+            top: Edge::Open,
+            ..arena()
+        };
+        let walls = arena.walls();
+        assert_eq!(walls.len(), 2);
+        assert!(walls.iter().all(|wall| wall.bottom() > arena.bounds.top()));
+
+        let wrapped = arena.wrap(Vec2::new(500.0, 39.0), RADIUS).unwrap();
+        assert_eq!(wrapped, Vec2::new(500.0, 660.0));
+        // Comes in right on the kill edge, which only counts once it is fully past
+        assert!(!arena.is_lost(wrapped, RADIUS));
+    }
+
+    #[test]
+    fn paddle_stays_on_the_arena_floor_and_between_its_sides() {
+        let arena = arena();
+        let start = arena.paddle_start(80.0, 20.0);
+        assert_eq!(start, Vec2::new(460.0, 620.0));
+
+        let (min_x, max_x) = arena.paddle_range(80.0);
+        assert_eq!((min_x, max_x), (110.0, 810.0));
+        // A paddle wider than the arena still gets a usable range
+        let (min_x, max_x) = arena.paddle_range(2000.0);
+        assert_eq!(min_x, max_x);
+    }
+
+    #[test]
+    fn obstacles_are_walls() {
+        let obstacle = Rect::new(400.0, 300.0, 50.0, 50.0);
+        let arena = Arena {
+            obstacles: vec![obstacle],
+            ..arena()
+        };
+        assert!(arena.walls().contains(&obstacle));
+    }
+}
@@ -0,0 +1 @@
+pub mod arena_class;
\ No newline at end of file
@@ -1,20 +1,79 @@
 use macroquad::prelude::*;
 
+use crate::arena::arena_class::Arena;
+use crate::collision::collision_class::{
+    SweepHit, circle_rect_contact, reflect, sweep_circle_rect,
+};
+use crate::player::player_class::{Player, PlayerLogic};
+use crate::tiles::tiles_class::{TileHit, TileMap};
+
+// Steepest launch off the paddle, measured from straight up (radians)
+const MAX_LAUNCH_ANGLE: f32 = std::f32::consts::PI / 3.0;
+// Angle used when the paddle is standing still, so the ball never serves straight up
+const STILL_LAUNCH_ANGLE: f32 = std::f32::consts::PI / 12.0;
+
+// Most bounces the ball may take within a single update before the rest of the step is dropped
+const MAX_BOUNCES_PER_STEP: usize = 8;
+// Rate at which spin dies out, per second
+const SPIN_DECAY: f32 = 1.5;
+// Distance the ball is moved off a surface after bouncing so it doesn't start the next sweep touching it
+const CONTACT_SKIN: f32 = 0.01;
+
+#[derive(Clone)]
 pub struct Ball {
     pub position: Vec2,
     pub radius: f32,
     pub velocity: Vec2,
     pub color: Color,
+    pub previous_position: Vec2,
+    // While held the ball rides on the paddle instead of moving on its own
+    pub held: bool,
+    // Horizontal offset from the paddle centre while held
+    pub hold_offset: f32,
+    // Curve applied to the ball's path, radians per second (positive turns clockwise)
+    pub spin: f32,
+}
+
+// Things that happen to the ball during `update` which the game has to react to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BallEvent {
+    // The ball bounced off the paddle
+    HitPaddle,
+    // The ball ran into a tile and damaged or destroyed it
+    HitTile(TileHit),
+    // The ball got past one of the arena's kill edges
+    Missed,
+}
+
+// What the ball ran into during one sweep of its path
+enum Obstacle {
+    Wall,
+    Paddle,
+    Tiles(Vec<(usize, usize)>),
 }
 
 pub trait BallLogic {
     fn new(initial_position: Vec2, radius: f32, velocity: Vec2, color: Color) -> Self;
 
-    fn update(&mut self, dt: f32, screen_width: f32, screen_height: f32);
+    fn update(
+        &mut self,
+        dt: f32,
+        arena: &Arena,
+        player: &Player,
+        tile_map: &mut TileMap,
+    ) -> Vec<BallEvent>;
+
+    fn hold(&mut self, offset_x: f32);
+
+    fn follow_paddle(&mut self, paddle_rect: Rect);
 
-    fn draw_ball(&self);
+    fn launch(&mut self, speed: f32, paddle_tilt: f32);
 
-    fn check_collision_and_bounce(&mut self, player_rect: Rect);
+    fn draw_ball(&self, alpha: f32);
+
+    fn check_collision_and_bounce(&mut self, player: &Player) -> bool;
+
+    fn bounce_off_paddle(&mut self, normal: Vec2, player: &Player);
 }
 
 impl BallLogic for Ball {
@@ -24,64 +83,403 @@ impl BallLogic for Ball {
             radius,
             velocity,
             color,
+            previous_position: initial_position,
+            held: false,
+            hold_offset: 0.0,
+            spin: 0.0,
         }
     }
 
-    fn update(&mut self, dt: f32, screen_width: f32, screen_height: f32) {
-        self.position += self.velocity * dt;
+    // Move the ball along its real path for the whole step. Each sweep finds the first
+    // wall, paddle or tile in the way, moves the ball up to it and bounces, then carries
+    // on with the time that is left, so fast balls can't tunnel through anything
+    fn update(
+        &mut self,
+        dt: f32,
+        arena: &Arena,
+        player: &Player,
+        tile_map: &mut TileMap,
+    ) -> Vec<BallEvent> {
+        let mut events = vec![];
+
+        if self.held {
+            return events;
+        }
+
+        // The paddle may have moved into the ball since the last step
+        if self.check_collision_and_bounce(player) {
+            events.push(BallEvent::HitPaddle);
+        }
+
+        // Spin bends the path a little every step and wears off over time
+        if self.spin != 0.0 {
+            self.velocity = Vec2::from_angle(self.spin * dt).rotate(self.velocity);
+            self.spin *= (-SPIN_DECAY * dt).exp();
+        }
+
+        let player_rect = player.get_player_rect();
+        let walls = arena.walls();
+        let mut remaining = dt;
+
+        for _ in 0..MAX_BOUNCES_PER_STEP {
+            let motion = self.velocity * remaining;
+            if motion == Vec2::ZERO {
+                break;
+            }
+
+            let mut earliest: Option<(SweepHit, Obstacle)> = None;
+            let mut consider = |hit: SweepHit, obstacle: Obstacle| {
+                if earliest
+                    .as_ref()
+                    .is_none_or(|(first, _)| hit.time < first.time)
+                {
+                    earliest = Some((hit, obstacle));
+                }
+            };
+
+            for &wall in &walls {
+                if let Some(hit) = sweep_circle_rect(self.position, motion, self.radius, wall) {
+                    consider(hit, Obstacle::Wall);
+                }
+            }
+            if let Some(hit) = sweep_circle_rect(self.position, motion, self.radius, player_rect) {
+                consider(hit, Obstacle::Paddle);
+            }
+            if let Some(sweep) = tile_map.sweep_ball(self.position, motion, self.radius) {
+                consider(sweep.hit, Obstacle::Tiles(sweep.tiles));
+            }
+
+            let Some((hit, obstacle)) = earliest else {
+                self.position += motion;
+                break;
+            };
+
+            self.position += motion * hit.time + hit.normal * CONTACT_SKIN;
+            remaining *= 1.0 - hit.time;
+
+            match obstacle {
+                Obstacle::Wall => self.velocity = reflect(self.velocity, hit.normal),
+                Obstacle::Paddle => {
+                    self.bounce_off_paddle(hit.normal, player);
+                    events.push(BallEvent::HitPaddle);
+                }
+                Obstacle::Tiles(tiles) => {
+                    let portal_exit = tiles.iter().find_map(|&(row, col)| {
+                        tile_map.portal_exit(row, col, self.velocity, self.radius, arena)
+                    });
 
-        // Bounce off screen edges
-        if self.position.x - self.radius <= 0.0 {
-            self.position.x = self.radius;
-            self.velocity.x = -self.velocity.x;
-        } else if self.position.x + self.radius >= screen_width {
-            self.position.x = screen_width - self.radius;
-            self.velocity.x = -self.velocity.x;
+                    if let Some(exit) = portal_exit {
+                        // Keep the velocity and jump straight to the other portal
+                        self.position = exit;
+                        self.previous_position = exit;
+                        continue;
+                    }
+
+                    self.velocity = reflect(self.velocity, hit.normal);
+                    for (row, col) in tiles {
+                        let hits = tile_map.hit_tile(row, col);
+                        events.extend(hits.into_iter().map(BallEvent::HitTile));
+                    }
+                }
+            }
         }
 
-        if self.position.y - self.radius <= 0.0 {
-            self.position.y = self.radius;
-            self.velocity.y = -self.velocity.y;
-        } else if self.position.y + self.radius >= screen_height {
-            self.position.y = screen_height - self.radius;
-            self.velocity.y = -self.velocity.y;
+        // Out through an open edge comes back in at the opposite one, out through a
+        // kill edge is a miss
+        if let Some(wrapped) = arena.wrap(self.position, self.radius) {
+            self.position = wrapped;
+            self.previous_position = wrapped;
+        }
+        if arena.is_lost(self.position, self.radius) {
+            events.push(BallEvent::Missed);
         }
+
+        events
     }
 
-    fn draw_ball(&self) {
-        draw_circle(self.position.x, self.position.y, self.radius, self.color);
+    // Stick the ball to the paddle, `offset_x` is measured from the paddle centre
+    fn hold(&mut self, offset_x: f32) {
+        self.held = true;
+        self.hold_offset = offset_x;
+        self.velocity = Vec2::ZERO;
     }
 
-    fn check_collision_and_bounce(&mut self, player_rect: Rect) {
-        // Find closest point in player rect to ball center
-        let closest_x = self.position.x.clamp(player_rect.x, player_rect.x + player_rect.w);
-        let closest_y = self.position.y.clamp(player_rect.y, player_rect.y + player_rect.h);
+    fn follow_paddle(&mut self, paddle_rect: Rect) {
+        if self.held {
+            self.position = Vec2::new(
+                paddle_rect.x + paddle_rect.w / 2.0 + self.hold_offset,
+                paddle_rect.y - self.radius,
+            );
+        }
+    }
 
-        let distance_x = self.position.x - closest_x;
-        let distance_y = self.position.y - closest_y;
+    // Release a held ball upwards. `paddle_tilt` is the paddle's velocity as a fraction
+    // of its top speed (-1..1), moving paddles throw the ball further to that side
+    fn launch(&mut self, speed: f32, paddle_tilt: f32) {
+        if !self.held {
+            return;
+        }
 
-        let distance_squared = distance_x * distance_x + distance_y * distance_y;
+        let tilt = paddle_tilt.clamp(-1.0, 1.0);
+        let angle = if tilt.abs() < 0.05 {
+            STILL_LAUNCH_ANGLE
+        } else {
+            tilt * MAX_LAUNCH_ANGLE
+        };
 
-        if distance_squared < self.radius * self.radius {
-            // Basic response: invert velocity depending on collision normal
+        self.held = false;
+        self.spin = 0.0;
+        self.velocity = Vec2::new(angle.sin(), -angle.cos()) * speed;
+    }
 
-            // Compute penetration depth
-            let distance = distance_squared.sqrt();
-            let penetration_depth = self.radius - distance;
+    fn draw_ball(&self, alpha: f32) {
+        let position = self.previous_position.lerp(self.position, alpha);
+        draw_circle(position.x, position.y, self.radius, self.color);
+    }
 
-            // Normalize collision vector (handle zero distance)
-            let normal = if distance == 0.0 {
-                Vec2::new(0.0, -1.0)
-            } else {
-                Vec2::new(distance_x / distance, distance_y / distance)
-            };
+    // Returns true when the ball touched the rect this frame
+    fn check_collision_and_bounce(&mut self, player: &Player) -> bool {
+        if self.held {
+            return false;
+        }
+
+        let player_rect = player.get_player_rect();
+        if let Some(contact) = circle_rect_contact(self.position, self.radius, player_rect) {
+            self.position += contact.normal * contact.penetration;
+            self.bounce_off_paddle(contact.normal, player);
+            return true;
+        }
 
-            // Push ball out of collision
-            self.position += normal * penetration_depth;
+        false
+    }
 
-            // Reflect velocity vector on the collision normal
-            let velocity_dot_normal = self.velocity.dot(normal);
-            self.velocity -= 2.0 * velocity_dot_normal * normal;
+    // Classic breakout aiming: where the ball lands along the paddle sets the angle it
+    // leaves at, from `min` in the middle to `max` at the ends, at the same speed.
+    // A moving paddle then pushes the ball along with it and puts spin on it.
+    // Hits on the paddle's sides are plain reflections
+    fn bounce_off_paddle(&mut self, normal: Vec2, player: &Player) {
+        if normal.y > -0.5 {
+            self.velocity = reflect(self.velocity, normal);
+            return;
         }
+
+        let player_rect = player.get_player_rect();
+        let half_width = player_rect.w / 2.0;
+        let offset = ((self.position.x - player_rect.center().x) / half_width).clamp(-1.0, 1.0);
+
+        // A dead-centre hit keeps going the way the ball came in
+        let angles = player.bounce_angles;
+        let side = if offset != 0.0 {
+            offset
+        } else {
+            self.velocity.x
+        };
+        let aimed = (angles.min + offset.abs() * (angles.max - angles.min)).copysign(side);
+
+        // English: the paddle's sideways motion tilts the outgoing direction
+        let speed = self.velocity.length();
+        let direction = Vec2::new(aimed.sin(), -aimed.cos()) * speed
+            + Vec2::new(player.velocity.x * player.english_transfer, 0.0);
+        let tilted = direction.x.atan2(-direction.y);
+
+        let angle = tilted.abs().clamp(angles.min, angles.max).copysign(tilted);
+        self.velocity = Vec2::new(angle.sin(), -angle.cos()) * speed;
+        self.spin = player.velocity.x * player.spin_transfer;
+    }
+}
+
+// Bounce balls off each other as equal-mass elastic collisions. Balls are sorted along x
+// and only pairs whose horizontal extents overlap are tested, so this stays cheap with
+// dozens of balls on screen. Held balls sit on the paddle and are left out
+pub fn resolve_ball_collisions(balls: &mut [Ball]) {
+    let mut order: Vec<usize> = (0..balls.len()).filter(|&i| !balls[i].held).collect();
+    order.sort_by(|&a, &b| {
+        let a_left = balls[a].position.x - balls[a].radius;
+        let b_left = balls[b].position.x - balls[b].radius;
+        a_left.total_cmp(&b_left)
+    });
+
+    for (index, &i) in order.iter().enumerate() {
+        for &j in &order[index + 1..] {
+            let right_edge = balls[i].position.x + balls[i].radius;
+            if balls[j].position.x - balls[j].radius > right_edge {
+                break;
+            }
+
+            let (a, b) = pair_mut(balls, i, j);
+            collide_pair(a, b);
+        }
+    }
+}
+
+fn pair_mut(balls: &mut [Ball], i: usize, j: usize) -> (&mut Ball, &mut Ball) {
+    if i < j {
+        let (left, right) = balls.split_at_mut(j);
+        (&mut left[i], &mut right[0])
+    } else {
+        let (left, right) = balls.split_at_mut(i);
+        (&mut right[0], &mut left[j])
+    }
+}
+
+fn collide_pair(a: &mut Ball, b: &mut Ball) {
+    let offset = b.position - a.position;
+    let distance = offset.length();
+    let overlap = a.radius + b.radius - distance;
+    if overlap <= 0.0 {
+        return;
+    }
+
+    let normal = if distance > 0.0 {
+        offset / distance
+    } else {
+        Vec2::X
+    };
+
+    // Positional correction: split the overlap evenly so they stop intersecting
+    a.position -= normal * (overlap / 2.0);
+    b.position += normal * (overlap / 2.0);
+
+    // Equal masses swap their velocity components along the normal, but only while
+    // they are still moving towards each other
+    let approach = (b.velocity - a.velocity).dot(normal);
+    if approach < 0.0 {
+        a.velocity += normal * approach;
+        b.velocity -= normal * approach;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ball(x: f32, y: f32, velocity: Vec2) -> Ball {
+        Ball::new(Vec2::new(x, y), 10.0, velocity, RED)
+    }
+
+    fn paddle() -> Player {
+        Player::new(
+            Vec2::new(100.0, 500.0),
+            250.0,
+            120.0,
+            2400.0,
+            3000.0,
+            false,
+            20.0,
+            80.0,
+        )
+    }
+
+    // Angle from straight up the ball leaves the paddle at after landing at `x`
+    fn bounce_angle(x: f32, velocity: Vec2) -> f32 {
+        let player = paddle();
+        let mut ball = ball(x, 490.0, velocity);
+        ball.bounce_off_paddle(Vec2::new(0.0, -1.0), &player);
+        ball.velocity.x.atan2(-ball.velocity.y)
+    }
+
+    #[test]
+    fn paddle_bounce_spreads_from_min_at_the_centre_to_max_at_the_ends() {
+        let angles = paddle().bounce_angles;
+        let close = |angle: f32, expected: f32| (angle - expected).abs() < 1e-4;
+
+        assert!(close(
+            bounce_angle(140.0, Vec2::new(50.0, 300.0)),
+            angles.min
+        ));
+        assert!(close(
+            bounce_angle(140.0, Vec2::new(-50.0, 300.0)),
+            -angles.min
+        ));
+        assert!(close(
+            bounce_angle(180.0, Vec2::new(0.0, 300.0)),
+            angles.max
+        ));
+        assert!(close(
+            bounce_angle(100.0, Vec2::new(0.0, 300.0)),
+            -angles.max
+        ));
+
+        // Just off centre already leaves at about `min`, there's no dead zone
+        let near_centre = bounce_angle(140.5, Vec2::new(-50.0, 300.0));
+        assert!(near_centre >= angles.min && near_centre < angles.min + 0.05);
+    }
+
+    #[test]
+    fn head_on_collision_swaps_normal_velocities() {
+        let mut a = ball(0.0, 0.0, Vec2::new(200.0, 30.0));
+        let mut b = ball(18.0, 0.0, Vec2::new(-100.0, -40.0));
+        let momentum = a.velocity + b.velocity;
+
+        collide_pair(&mut a, &mut b);
+
+        // Along the normal (x) the velocities swap, across it (y) they are kept
+        assert_eq!(a.velocity, Vec2::new(-100.0, 30.0));
+        assert_eq!(b.velocity, Vec2::new(200.0, -40.0));
+        assert_eq!(a.velocity + b.velocity, momentum);
+    }
+
+    #[test]
+    fn collision_removes_the_overlap() {
+        let mut a = ball(0.0, 0.0, Vec2::new(50.0, 50.0));
+        let mut b = ball(6.0, 8.0, Vec2::new(-50.0, -50.0));
+
+        collide_pair(&mut a, &mut b);
+
+        let distance = a.position.distance(b.position);
+        assert!(
+            (distance - (a.radius + b.radius)).abs() < 1e-4,
+            "{distance}"
+        );
+    }
+
+    #[test]
+    fn separating_balls_keep_their_velocities() {
+        let mut a = ball(0.0, 0.0, Vec2::new(-100.0, 0.0));
+        let mut b = ball(15.0, 0.0, Vec2::new(100.0, 20.0));
+
+        collide_pair(&mut a, &mut b);
+
+        assert_eq!(a.velocity, Vec2::new(-100.0, 0.0));
+        assert_eq!(b.velocity, Vec2::new(100.0, 20.0));
+    }
+
+    #[test]
+    fn held_balls_are_left_out_of_collisions() {
+        let mut held = ball(0.0, 0.0, Vec2::ZERO);
+        held.hold(0.0);
+        let mut balls = vec![
+            held,
+            ball(5.0, 0.0, Vec2::new(-100.0, 0.0)),
+            ball(100.0, 0.0, Vec2::new(50.0, 0.0)),
+        ];
+        let before: Vec<_> = balls
+            .iter()
+            .map(|ball| (ball.position, ball.velocity))
+            .collect();
+
+        resolve_ball_collisions(&mut balls);
+
+        let after: Vec<_> = balls
+            .iter()
+            .map(|ball| (ball.position, ball.velocity))
+            .collect();
+        assert_eq!(after, before);
+    }
+
+    #[test]
+    fn overlapping_free_balls_are_resolved() {
+        let mut balls = vec![
+            ball(300.0, 0.0, Vec2::new(-10.0, 0.0)),
+            ball(0.0, 0.0, Vec2::new(10.0, 0.0)),
+            ball(15.0, 0.0, Vec2::new(-10.0, 0.0)),
+        ];
+
+        resolve_ball_collisions(&mut balls);
+
+        assert_eq!(balls[0].velocity, Vec2::new(-10.0, 0.0));
+        assert_eq!(balls[1].velocity, Vec2::new(-10.0, 0.0));
+        assert_eq!(balls[2].velocity, Vec2::new(10.0, 0.0));
+        assert!(balls[1].position.distance(balls[2].position) >= 20.0 - 1e-4);
     }
 }
@@ -0,0 +1,220 @@
+use macroquad::prelude::*;
+
+// Result of a circle overlapping a rectangle: the direction that leads out of the
+// rectangle and how far the circle has to move along it to stop touching
+#[derive(Debug, Clone, Copy)]
+pub struct Contact {
+    pub normal: Vec2,
+    pub penetration: f32,
+}
+
+// Circle-vs-rect test shared by the paddle and the tiles.
+// Hitting a face gives that face's normal, hitting a corner gives the diagonal from
+// the corner to the circle centre, so corner hits deflect the ball at an angle
+pub fn circle_rect_contact(center: Vec2, radius: f32, rect: Rect) -> Option<Contact> {
+    // Find closest point in the rect to the circle center
+    let closest = Vec2::new(
+        center.x.clamp(rect.x, rect.x + rect.w),
+        center.y.clamp(rect.y, rect.y + rect.h),
+    );
+
+    let offset = center - closest;
+    let distance_squared = offset.length_squared();
+
+    if distance_squared >= radius * radius {
+        return None;
+    }
+
+    if distance_squared > 0.0 {
+        let distance = distance_squared.sqrt();
+        return Some(Contact {
+            normal: offset / distance,
+            penetration: radius - distance,
+        });
+    }
+
+    // The centre is inside the rect, push it out through the nearest face
+    let to_left = center.x - rect.x;
+    let to_right = rect.x + rect.w - center.x;
+    let to_top = center.y - rect.y;
+    let to_bottom = rect.y + rect.h - center.y;
+    let nearest = to_left.min(to_right).min(to_top).min(to_bottom);
+
+    let normal = if nearest == to_top {
+        Vec2::new(0.0, -1.0)
+    } else if nearest == to_bottom {
+        Vec2::new(0.0, 1.0)
+    } else if nearest == to_left {
+        Vec2::new(-1.0, 0.0)
+    } else {
+        Vec2::new(1.0, 0.0)
+    };
+
+    Some(Contact {
+        normal,
+        penetration: nearest + radius,
+    })
+}
+
+// Hits closer together in time than this (as a fraction of the step) count as one
+const SIMULTANEOUS_HIT_TIME: f32 = 1e-4;
+
+// First touch of a moving circle with a rect, `time` is the fraction of the motion
+// travelled before touching (0..=1) and `normal` points out of the rect
+#[derive(Debug, Clone, Copy)]
+pub struct SweepHit {
+    pub time: f32,
+    pub normal: Vec2,
+}
+
+// Swept circle-vs-rect test. The circle moving from `start` by `motion` is treated as a
+// ray against the rect grown by the radius with rounded corners, so fast circles can't
+// skip over thin rects. A circle already overlapping the rect hits at time 0, but only
+// while it is still moving further in
+pub fn sweep_circle_rect(start: Vec2, motion: Vec2, radius: f32, rect: Rect) -> Option<SweepHit> {
+    if let Some(contact) = circle_rect_contact(start, radius, rect) {
+        if motion.dot(contact.normal) < 0.0 {
+            return Some(SweepHit {
+                time: 0.0,
+                normal: contact.normal,
+            });
+        }
+        return None;
+    }
+
+    let expanded = Rect::new(
+        rect.x - radius,
+        rect.y - radius,
+        rect.w + radius * 2.0,
+        rect.h + radius * 2.0,
+    );
+
+    // Where the ray enters the grown rect, or the start itself when it is already in
+    // one of the corner squares that the rounded corners don't fill
+    let (time, normal) = ray_rect(start, motion, expanded)?;
+    let point = start + motion * time;
+
+    let within_x = point.x >= rect.x && point.x <= rect.x + rect.w;
+    let within_y = point.y >= rect.y && point.y <= rect.y + rect.h;
+    if (within_x || within_y) && normal != Vec2::ZERO {
+        return Some(SweepHit { time, normal });
+    }
+
+    // The entry point is in a corner square, test against that corner's circle
+    let corner = Vec2::new(
+        if point.x < rect.x {
+            rect.x
+        } else {
+            rect.x + rect.w
+        },
+        if point.y < rect.y {
+            rect.y
+        } else {
+            rect.y + rect.h
+        },
+    );
+    let time = ray_circle(start, motion, corner, radius)?;
+    let normal = (start + motion * time - corner).try_normalize()?;
+
+    Some(SweepHit { time, normal })
+}
+
+// Slab test of a ray against a rect, returning the entry time (0..=1) and the normal of
+// the face it enters through. A start inside the rect enters at time 0 with no face
+fn ray_rect(start: Vec2, motion: Vec2, rect: Rect) -> Option<(f32, Vec2)> {
+    let mut entry = f32::NEG_INFINITY;
+    let mut exit = f32::INFINITY;
+    let mut normal = Vec2::ZERO;
+
+    for (origin, delta, min, max, axis) in [
+        (start.x, motion.x, rect.x, rect.x + rect.w, Vec2::X),
+        (start.y, motion.y, rect.y, rect.y + rect.h, Vec2::Y),
+    ] {
+        if delta == 0.0 {
+            if origin < min || origin > max {
+                return None;
+            }
+            continue;
+        }
+
+        let t_min = (min - origin) / delta;
+        let t_max = (max - origin) / delta;
+        let (near, far) = if t_min < t_max {
+            (t_min, t_max)
+        } else {
+            (t_max, t_min)
+        };
+
+        if near > entry {
+            entry = near;
+            // Moving in +axis enters through the min face, whose normal points -axis
+            normal = -axis * delta.signum();
+        }
+        exit = exit.min(far);
+    }
+
+    if entry > exit || entry > 1.0 || exit < 0.0 {
+        return None;
+    }
+
+    if entry < 0.0 {
+        return Some((0.0, Vec2::ZERO));
+    }
+
+    Some((entry, normal))
+}
+
+// Earliest time (0..=1) a ray hits a circle, if it does within the motion
+fn ray_circle(start: Vec2, motion: Vec2, center: Vec2, radius: f32) -> Option<f32> {
+    let offset = start - center;
+    let a = motion.dot(motion);
+    let b = 2.0 * offset.dot(motion);
+    let c = offset.dot(offset) - radius * radius;
+
+    let discriminant = b * b - 4.0 * a * c;
+    if a == 0.0 || discriminant < 0.0 {
+        return None;
+    }
+
+    let time = (-b - discriminant.sqrt()) / (2.0 * a);
+    (0.0..=1.0).contains(&time).then_some(time)
+}
+
+// Merge hits against several rects into the earliest one. Every hit at (almost) that
+// same time has its normal blended in, so a ball reaching two tiles across a seam
+// bounces as if off a single flat wall
+pub fn combine_hits(hits: &[SweepHit]) -> Option<SweepHit> {
+    let earliest = hits
+        .iter()
+        .copied()
+        .min_by(|a, b| a.time.total_cmp(&b.time))?;
+
+    let blended: Vec2 = hits
+        .iter()
+        .filter(|hit| is_simultaneous(hit.time, earliest.time))
+        .map(|hit| hit.normal)
+        .sum();
+
+    // Hits on opposite sides cancel out, fall back to the earliest one
+    let normal = blended.try_normalize().unwrap_or(earliest.normal);
+
+    Some(SweepHit {
+        time: earliest.time,
+        normal,
+    })
+}
+
+pub fn is_simultaneous(time: f32, other: f32) -> bool {
+    (time - other).abs() <= SIMULTANEOUS_HIT_TIME
+}
+
+// Mirror a velocity on a surface normal. Velocities already leaving the surface are
+// left alone, so touching the same surface on consecutive frames can't flip twice
+pub fn reflect(velocity: Vec2, normal: Vec2) -> Vec2 {
+    let velocity_dot_normal = velocity.dot(normal);
+    if velocity_dot_normal >= 0.0 {
+        return velocity;
+    }
+
+    velocity - 2.0 * velocity_dot_normal * normal
+}
@@ -0,0 +1 @@
+pub mod collision_class;
\ No newline at end of file
@@ -0,0 +1,547 @@
+use macroquad::prelude::*;
+
+use crate::arena::arena_class::Arena;
+use crate::ball::ball_class::{Ball, BallEvent, BallLogic, resolve_ball_collisions};
+use crate::laser::laser_class::Laser;
+use crate::levels::levels_class::Level;
+use crate::player::player_class::{Player, PlayerLogic};
+use crate::powerups::powerups_class::{PowerUpKind, PowerUps};
+use crate::score::score_class::Score;
+use crate::sound::sound_class::TileSounds;
+use crate::tiles::tiles_class::TileMap;
+use crate::viewport::viewport_class::Viewport;
+use crate::{SCREEN_HEIGHT, SCREEN_WIDTH};
+
+const STARTING_LIVES: u32 = 3;
+
+// The simulation always advances in steps of this length, whatever the frame rate
+const TICK_RATE: f32 = 120.0;
+const FIXED_DT: f32 = 1.0 / TICK_RATE;
+// Longest frame we try to catch up on, so a long hitch doesn't stall on hundreds of ticks
+const MAX_FRAME_TIME: f32 = 0.25;
+
+// Angle between the ball a multi-ball splits from and each of the two new ones
+const MULTI_BALL_SPREAD: f32 = 0.35;
+// Space left between the new balls and the one they split from
+const MULTI_BALL_GAP: f32 = 1.0;
+
+// Top-level state of the game, every state has its own update and draw step
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameState {
+    Title,
+    Serve,
+    Playing,
+    Paused,
+    LevelClear,
+    GameOver,
+}
+
+// Key presses seen since the last simulation tick. Presses are sampled every frame and
+// consumed by the next tick, so none are lost or repeated however many ticks a frame runs
+#[derive(Default)]
+struct TickInput {
+    launch: bool,
+    pause: bool,
+    confirm: bool,
+    fire: bool,
+}
+
+impl TickInput {
+    fn sample(&mut self, viewport: &Viewport) {
+        // Clicks only count inside the playfield, not on the letterbox bars
+        let click =
+            is_mouse_button_pressed(MouseButton::Left) && viewport.mouse_position().is_some();
+
+        self.launch |= is_key_pressed(KeyCode::Space) || click;
+        self.pause |= is_key_pressed(KeyCode::P) || is_key_pressed(KeyCode::Escape);
+        self.confirm |= is_key_pressed(KeyCode::Enter) || is_key_pressed(KeyCode::Space) || click;
+        // Held rather than pressed, the laser keeps firing at its own rate
+        self.fire |= is_key_down(KeyCode::F);
+    }
+}
+
+pub struct Game {
+    pub state: GameState,
+    pub player: Player,
+    // Every ball in play, a life is only lost once the last one is gone
+    pub balls: Vec<Ball>,
+    // Copy of the starting ball, each serve begins with a fresh one
+    serve_ball: Ball,
+    pub tile_map: TileMap,
+    pub arena: Arena,
+    pub lives: u32,
+    pub score: Score,
+    sounds: TileSounds,
+    pub levels: Vec<Level>,
+    pub current_level: usize,
+    pub powerups: PowerUps,
+    pub laser: Laser,
+    player_start: Vec2,
+    // Paddle width without any power-up applied
+    base_paddle_width: f32,
+    // Ball speed multiplier currently applied to the balls in play
+    ball_speed_scale: f32,
+    input: TickInput,
+    // Where the playfield is drawn in the window, refitted every frame
+    viewport: Viewport,
+    // Simulation time owed to the fixed-step loop, carried over between frames
+    accumulator: f32,
+}
+
+fn draw_centered_text(text: &str, y: f32, font_size: u16, color: Color) {
+    let dims = measure_text(text, None, font_size, 1.0);
+    draw_text(
+        text,
+        SCREEN_WIDTH / 2.0 - dims.width / 2.0,
+        y,
+        font_size as f32,
+        color,
+    );
+}
+
+impl Game {
+    pub fn new(
+        player: Player,
+        ball: Ball,
+        levels: Vec<Level>,
+        arena: Arena,
+        sounds: TileSounds,
+    ) -> Self {
+        let tile_map = levels[0].build_tile_map(arena.bounds);
+
+        Game {
+            state: GameState::Title,
+            player_start: player.position_vector,
+            base_paddle_width: player.player_width,
+            player,
+            balls: vec![ball.clone()],
+            serve_ball: ball,
+            tile_map,
+            arena,
+            lives: STARTING_LIVES,
+            score: Score::new(),
+            sounds,
+            levels,
+            current_level: 0,
+            powerups: PowerUps::new(),
+            laser: Laser::new(),
+            ball_speed_scale: 1.0,
+            input: TickInput::default(),
+            viewport: Viewport::fit_window(),
+            accumulator: 0.0,
+        }
+    }
+
+    // Switch to another state, running whatever that state needs on entry
+    fn enter(&mut self, state: GameState) {
+        if state == GameState::Serve {
+            // Power-ups and burning fuses don't carry over into a new serve
+            self.powerups.clear();
+            self.laser.clear();
+            self.tile_map.clear_explosions();
+            self.apply_power_ups();
+
+            // Every serve, after a lost life or on a new level, starts with one ball held
+            let mut ball = self.serve_ball.clone();
+            ball.hold(0.0);
+            ball.follow_paddle(self.player.get_player_rect());
+            ball.previous_position = ball.position;
+            self.balls = vec![ball];
+        }
+        self.state = state;
+    }
+
+    fn start_new_game(&mut self) {
+        self.lives = STARTING_LIVES;
+        self.score.reset();
+        self.load_level(0);
+    }
+
+    fn level(&self) -> &Level {
+        &self.levels[self.current_level]
+    }
+
+    fn is_last_level(&self) -> bool {
+        self.current_level + 1 >= self.levels.len()
+    }
+
+    // Build the level's tiles and put the paddle and ball back at the start
+    fn load_level(&mut self, index: usize) {
+        self.current_level = index;
+        self.tile_map = self.level().build_tile_map(self.arena.bounds);
+        self.player.reset_to(self.player_start);
+        self.enter(GameState::Serve);
+    }
+
+    fn move_player(&mut self, dt: f32) {
+        self.player.control_rectangle(dt, None, &self.arena);
+    }
+
+    // Carry held balls along with the paddle and let them go when launch is pressed.
+    // Returns true if any ball was launched
+    fn update_held_balls(&mut self) -> bool {
+        let player_rect = self.player.get_player_rect();
+        let launch = self.input.launch;
+        let tilt = self.player.velocity.x / self.player.movement_speed;
+        let speed = self.level().ball_speed * self.ball_speed_scale;
+        let mut launched = false;
+
+        for ball in self.balls.iter_mut().filter(|ball| ball.held) {
+            ball.follow_paddle(player_rect);
+            if launch {
+                ball.launch(speed, tilt);
+                launched = true;
+            }
+        }
+
+        launched
+    }
+
+    // Paddle width and ball speed follow whichever effects are running. They are worked
+    // out from the base values every tick, so an effect ending always puts them back
+    fn apply_power_ups(&mut self) {
+        let width = self.base_paddle_width * self.powerups.paddle_width_scale();
+        if width != self.player.player_width {
+            // Grow or shrink around the paddle's centre, staying in the arena
+            let center = self.player.position_vector.x + self.player.player_width / 2.0;
+            let (min_x, max_x) = self.arena.paddle_range(width);
+            self.player.player_width = width;
+            self.player.position_vector.x = (center - width / 2.0).clamp(min_x, max_x);
+        }
+
+        let speed_scale = self.powerups.ball_speed_scale();
+        if speed_scale != self.ball_speed_scale {
+            for ball in &mut self.balls {
+                ball.velocity *= speed_scale / self.ball_speed_scale;
+            }
+            self.ball_speed_scale = speed_scale;
+        }
+
+        if !self.powerups.is_active(PowerUpKind::Laser) {
+            self.laser.disarm();
+        }
+    }
+
+    fn catch_power_up(&mut self, kind: PowerUpKind) {
+        self.powerups.activate(kind);
+        match kind {
+            PowerUpKind::ExtraLife => self.lives += 1,
+            PowerUpKind::MultiBall => self.split_ball(),
+            PowerUpKind::Laser => self.laser.arm(),
+            _ => {}
+        }
+    }
+
+    // Add two balls going off either side of the first ball in flight. They start beside
+    // it rather than on top of it, so ball-ball collisions don't knock them off course
+    fn split_ball(&mut self) {
+        let Some(ball) = self.balls.iter().find(|ball| !ball.held).cloned() else {
+            return;
+        };
+
+        let Some(across) = ball
+            .velocity
+            .try_normalize()
+            .map(|direction| direction.perp())
+        else {
+            return;
+        };
+        let spacing = ball.radius * 2.0 + MULTI_BALL_GAP;
+        let fits = |position: Vec2| {
+            self.arena.has_room_for(position, ball.radius)
+                && self.tile_map.is_clear(position, ball.radius)
+        };
+
+        let mut splits = vec![];
+        for angle in [-MULTI_BALL_SPREAD, MULTI_BALL_SPREAD] {
+            // Each new ball goes on the side it turns towards, or twice as far out on the
+            // other side when there's no room. With no room on either side it isn't added
+            let side = across * angle.signum();
+            let candidates = [
+                ball.position + side * spacing,
+                ball.position - side * spacing * 2.0,
+            ];
+            let Some(position) = candidates.into_iter().find(|&position| fits(position)) else {
+                continue;
+            };
+
+            let mut split = ball.clone();
+            split.position = position;
+            split.previous_position = position;
+            split.velocity = Vec2::from_angle(angle).rotate(ball.velocity);
+            splits.push(split);
+        }
+        self.balls.extend(splits);
+    }
+
+    // Run as many fixed steps as the frame time covers, the remainder is carried over
+    // and used to interpolate the drawing between the last two steps
+    pub fn update(&mut self, frame_time: f32) {
+        self.viewport = Viewport::fit_window();
+        self.input.sample(&self.viewport);
+        self.accumulator += frame_time.min(MAX_FRAME_TIME);
+
+        while self.accumulator >= FIXED_DT {
+            self.tick(FIXED_DT);
+            self.accumulator -= FIXED_DT;
+            self.input = TickInput::default();
+        }
+    }
+
+    // How far rendering is between the previous and the current step (0..1). Everything
+    // that moves keeps a `previous_position` from the start of the last step, and its
+    // draw function takes this as `alpha` to draw it partway towards `position`, so
+    // motion looks smooth whatever the frame rate
+    fn interpolation(&self) -> f32 {
+        self.accumulator / FIXED_DT
+    }
+
+    fn tick(&mut self, dt: f32) {
+        for ball in &mut self.balls {
+            ball.previous_position = ball.position;
+        }
+        for projectile in &mut self.laser.projectiles {
+            projectile.previous_position = projectile.position;
+        }
+        self.player.previous_position = self.player.position_vector;
+
+        match self.state {
+            GameState::Title => self.update_title(),
+            GameState::Serve => self.update_serve(dt),
+            GameState::Playing => self.update_playing(dt),
+            GameState::Paused => self.update_paused(),
+            GameState::LevelClear => self.update_level_clear(),
+            GameState::GameOver => self.update_game_over(),
+        }
+    }
+
+    fn update_title(&mut self) {
+        if self.input.confirm {
+            self.start_new_game();
+        }
+    }
+
+    fn update_serve(&mut self, dt: f32) {
+        self.apply_power_ups();
+        self.move_player(dt);
+        if self.update_held_balls() {
+            self.enter(GameState::Playing);
+        }
+    }
+
+    fn update_playing(&mut self, dt: f32) {
+        if self.input.pause {
+            self.enter(GameState::Paused);
+            return;
+        }
+
+        self.score.update(dt);
+
+        let caught = self
+            .powerups
+            .update(dt, self.player.get_player_rect(), &self.arena);
+        for kind in caught {
+            self.catch_power_up(kind);
+        }
+        self.apply_power_ups();
+
+        self.move_player(dt);
+        self.update_held_balls();
+
+        // Move every ball, bouncing them off walls, the paddle and tiles (which get
+        // destroyed). Balls that fall out of the playfield are removed
+        let arena = &self.arena;
+        let player = &self.player;
+        let player_rect = player.get_player_rect();
+        let sticky = self.powerups.is_active(PowerUpKind::Sticky);
+        let tile_map = &mut self.tile_map;
+        let mut events = vec![];
+
+        self.balls.retain_mut(|ball| {
+            let ball_events = ball.update(dt, arena, player, tile_map);
+            if sticky && ball_events.contains(&BallEvent::HitPaddle) {
+                // A sticky paddle catches the ball where it landed until it is launched
+                let half_width = player_rect.w / 2.0;
+                let offset = ball.position.x - (player_rect.x + half_width);
+                ball.hold(offset.clamp(-half_width, half_width));
+                ball.follow_paddle(player_rect);
+            }
+            let missed = ball_events.contains(&BallEvent::Missed);
+            events.extend(ball_events);
+            !missed
+        });
+        resolve_ball_collisions(&mut self.balls);
+
+        if self.input.fire {
+            self.laser.fire(self.player.get_player_rect());
+        }
+        let laser_hits = self.laser.update(dt, &self.arena, &mut self.tile_map);
+        events.extend(laser_hits.into_iter().map(BallEvent::HitTile));
+
+        // Chained explosions go off on their own timers and score like any other hit
+        let explosion_hits = self.tile_map.update(dt);
+        events.extend(explosion_hits.into_iter().map(BallEvent::HitTile));
+
+        for event in events {
+            match event {
+                BallEvent::HitTile(hit) => {
+                    let tile_rect = self.tile_map.tiles[hit.row][hit.col].get_rect();
+                    self.score.register_hit(&hit, tile_rect.center());
+                    self.sounds.play_hit(&hit);
+                    self.powerups.spawn_from(&hit, tile_rect.center());
+                }
+                BallEvent::HitPaddle => self.score.break_combo(),
+                BallEvent::Missed => {}
+            }
+        }
+
+        // Clearing the level wins even if the last ball was lost on the same tick
+        if self.tile_map.is_cleared() {
+            self.enter(GameState::LevelClear);
+            return;
+        }
+
+        if self.balls.is_empty() {
+            self.score.break_combo();
+            self.lives -= 1;
+            if self.lives > 0 {
+                self.enter(GameState::Serve);
+            } else {
+                self.enter(GameState::GameOver);
+            }
+        }
+    }
+
+    fn update_paused(&mut self) {
+        if self.input.pause {
+            self.enter(GameState::Playing);
+        }
+    }
+
+    fn update_level_clear(&mut self) {
+        if self.input.confirm {
+            if self.is_last_level() {
+                self.enter(GameState::Title);
+            } else {
+                self.load_level(self.current_level + 1);
+            }
+        }
+    }
+
+    fn update_game_over(&mut self) {
+        if self.input.confirm {
+            self.enter(GameState::Title);
+        }
+    }
+
+    pub fn draw(&self) {
+        // The letterbox bars, then everything else in playfield coordinates
+        clear_background(BLACK);
+        set_camera(&self.viewport.camera());
+        draw_rectangle(0.0, 0.0, SCREEN_WIDTH, SCREEN_HEIGHT, WHITE);
+
+        match self.state {
+            GameState::Title => self.draw_title(),
+            GameState::Serve => self.draw_serve(),
+            GameState::Playing => self.draw_world(),
+            GameState::Paused => self.draw_paused(),
+            GameState::LevelClear => self.draw_level_clear(),
+            GameState::GameOver => self.draw_game_over(),
+        }
+
+        set_default_camera();
+    }
+
+    // Tiles, paddle, ball and the HUD, shared by every in-game state
+    fn draw_world(&self) {
+        // Draw tiles first (background)
+        self.arena.draw();
+        self.tile_map.draw();
+        self.powerups.draw();
+        let alpha = self.interpolation();
+        self.player.draw_player(BLUE, alpha);
+        for ball in &self.balls {
+            ball.draw_ball(alpha);
+        }
+        self.laser.draw(alpha);
+        self.score.draw_popups();
+
+        draw_text(
+            &format!("Lives: {}", self.lives),
+            20.0,
+            SCREEN_HEIGHT - 20.0,
+            30.0,
+            BLACK,
+        );
+        draw_text(
+            &format!("Level {}: {}", self.current_level + 1, self.level().name),
+            20.0,
+            SCREEN_HEIGHT - 50.0,
+            24.0,
+            DARKGRAY,
+        );
+        self.powerups.draw_indicators(200.0, SCREEN_HEIGHT - 20.0);
+        if self.laser.is_armed() {
+            draw_text(
+                &format!("Laser: {} (F)", self.laser.ammo),
+                SCREEN_WIDTH - 220.0,
+                SCREEN_HEIGHT - 80.0,
+                24.0,
+                RED,
+            );
+        }
+        self.score.draw(SCREEN_WIDTH - 220.0, SCREEN_HEIGHT - 50.0);
+    }
+
+    fn draw_title(&self) {
+        let h = SCREEN_HEIGHT;
+        draw_centered_text("BREAKOUT", h / 2.0 - 40.0, 80, DARKGREEN);
+        draw_centered_text("Press Enter to start", h / 2.0 + 30.0, 30, BLACK);
+    }
+
+    fn draw_serve(&self) {
+        self.draw_world();
+        draw_centered_text("Press Space to launch", SCREEN_HEIGHT / 2.0, 30, BLACK);
+    }
+
+    fn draw_paused(&self) {
+        self.draw_world();
+        draw_rectangle(
+            0.0,
+            0.0,
+            SCREEN_WIDTH,
+            SCREEN_HEIGHT,
+            Color::new(0.0, 0.0, 0.0, 0.4),
+        );
+        draw_centered_text("PAUSED", SCREEN_HEIGHT / 2.0, 60, WHITE);
+    }
+
+    fn draw_level_clear(&self) {
+        self.draw_world();
+        let h = SCREEN_HEIGHT;
+        if self.is_last_level() {
+            draw_centered_text("YOU WIN!", h / 2.0, 60, DARKGREEN);
+            draw_centered_text(
+                "Press Enter to return to the title",
+                h / 2.0 + 40.0,
+                30,
+                BLACK,
+            );
+        } else {
+            draw_centered_text("LEVEL CLEAR", h / 2.0, 60, DARKGREEN);
+            draw_centered_text("Press Enter for the next level", h / 2.0 + 40.0, 30, BLACK);
+        }
+    }
+
+    fn draw_game_over(&self) {
+        self.draw_world();
+        let h = SCREEN_HEIGHT;
+        draw_centered_text("GAME OVER", h / 2.0, 60, RED);
+        draw_centered_text(
+            "Press Enter to return to the title",
+            h / 2.0 + 40.0,
+            30,
+            BLACK,
+        );
+    }
+}
@@ -0,0 +1 @@
+pub mod game_class;
\ No newline at end of file
@@ -0,0 +1,152 @@
+use macroquad::prelude::*;
+
+use crate::arena::arena_class::Arena;
+use crate::tiles::tiles_class::{TileHit, TileMap};
+
+// Shots a laser pickup gives, every shot fires one projectile from each end of the paddle
+const LASER_AMMO: u32 = 20;
+// Seconds between shots while the fire key is held
+const FIRE_COOLDOWN: f32 = 0.3;
+
+// Pixels per second, straight up
+const PROJECTILE_SPEED: f32 = 700.0;
+const PROJECTILE_WIDTH: f32 = 4.0;
+const PROJECTILE_HEIGHT: f32 = 14.0;
+// How far in from each end of the paddle the projectiles start
+const MUZZLE_INSET: f32 = 6.0;
+
+// A single laser shot. Moves and draws like a ball, `position` is its top-left corner
+pub struct Projectile {
+    pub position: Vec2,
+    pub previous_position: Vec2,
+    pub velocity: Vec2,
+}
+
+impl Projectile {
+    pub fn new(position: Vec2) -> Self {
+        Projectile {
+            position,
+            previous_position: position,
+            velocity: Vec2::new(0.0, -PROJECTILE_SPEED),
+        }
+    }
+
+    pub fn get_rect(&self) -> Rect {
+        Rect::new(
+            self.position.x,
+            self.position.y,
+            PROJECTILE_WIDTH,
+            PROJECTILE_HEIGHT,
+        )
+    }
+
+    // Move one step. Returns the tile it ran into, if any: the lowest solid tile in the
+    // strip it covered this step, found through the tile grid
+    fn update(&mut self, dt: f32, tile_map: &TileMap) -> Option<(usize, usize)> {
+        let start = self.get_rect();
+        self.position += self.velocity * dt;
+        let swept = start.combine_with(self.get_rect());
+
+        tile_map
+            .cells_in_rect(swept)
+            .filter(|&(row, col)| {
+                let tile = &tile_map.tiles[row][col];
+                tile.is_solid && !tile.destroyed && tile.get_rect().overlaps(&swept)
+            })
+            .max_by(|&(a_row, a_col), &(b_row, b_col)| {
+                let a = tile_map.tiles[a_row][a_col].get_rect().bottom();
+                let b = tile_map.tiles[b_row][b_col].get_rect().bottom();
+                a.total_cmp(&b)
+            })
+    }
+
+    pub fn draw(&self, alpha: f32) {
+        let position = self.previous_position.lerp(self.position, alpha);
+        draw_rectangle(
+            position.x,
+            position.y,
+            PROJECTILE_WIDTH,
+            PROJECTILE_HEIGHT,
+            RED,
+        );
+    }
+}
+
+// The paddle's weapon: its remaining ammo, the fire-rate cooldown and shots in flight
+#[derive(Default)]
+pub struct Laser {
+    pub projectiles: Vec<Projectile>,
+    pub ammo: u32,
+    cooldown: f32,
+}
+
+impl Laser {
+    pub fn new() -> Self {
+        Laser::default()
+    }
+
+    // Refill the ammo when a laser power-up is caught
+    pub fn arm(&mut self) {
+        self.ammo = LASER_AMMO;
+    }
+
+    // Put the weapon away, shots already fired keep flying
+    pub fn disarm(&mut self) {
+        self.ammo = 0;
+    }
+
+    pub fn is_armed(&self) -> bool {
+        self.ammo > 0
+    }
+
+    pub fn clear(&mut self) {
+        self.projectiles.clear();
+        self.ammo = 0;
+        self.cooldown = 0.0;
+    }
+
+    // Fire from both ends of the paddle if the cooldown is over and there is ammo left
+    pub fn fire(&mut self, paddle_rect: Rect) {
+        if self.cooldown > 0.0 || !self.is_armed() {
+            return;
+        }
+
+        let y = paddle_rect.y - PROJECTILE_HEIGHT;
+        let left = paddle_rect.x + MUZZLE_INSET;
+        let right = paddle_rect.right() - MUZZLE_INSET - PROJECTILE_WIDTH;
+        for x in [left, right] {
+            self.projectiles.push(Projectile::new(Vec2::new(x, y)));
+        }
+
+        self.ammo -= 1;
+        self.cooldown = FIRE_COOLDOWN;
+    }
+
+    // Move every shot, each one is used up on the first tile or obstacle it hits or once
+    // it leaves the arena. Returns the tiles that were damaged
+    pub fn update(&mut self, dt: f32, arena: &Arena, tile_map: &mut TileMap) -> Vec<TileHit> {
+        self.cooldown = (self.cooldown - dt).max(0.0);
+
+        let mut hits = vec![];
+        self.projectiles.retain_mut(|projectile| {
+            if let Some((row, col)) = projectile.update(dt, tile_map) {
+                hits.extend(tile_map.hit_tile(row, col));
+                return false;
+            }
+            let rect = projectile.get_rect();
+            let blocked = arena
+                .obstacles
+                .iter()
+                .any(|obstacle| obstacle.overlaps(&rect));
+            !blocked && !arena.is_outside(rect)
+        });
+
+        hits
+    }
+
+    pub fn draw(&self, alpha: f32) {
+        for projectile in &self.projectiles {
+            projectile.draw(alpha);
+        }
+    }
+}
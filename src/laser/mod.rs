@@ -0,0 +1 @@
+pub mod laser_class;
\ No newline at end of file
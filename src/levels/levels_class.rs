@@ -0,0 +1,77 @@
+use macroquad::prelude::*;
+
+use crate::tiles::tiles_class::{ImageLevelOptions, LevelParseError, TileLayout, TileMap};
+
+// One stage of the campaign: its tiles plus how it is played
+pub struct Level {
+    pub name: String,
+    pub ball_speed: f32,
+    pub layout: TileLayout,
+    // Fresh copy of the level's tiles, cloned every time the level is played
+    tile_map: TileMap,
+}
+
+impl Level {
+    // Load a level written in the text format read by `TileMap::from_level_str`
+    pub fn from_level_str(source: &str) -> Result<Self, LevelParseError> {
+        let (header, tile_map) = TileMap::from_level_str(source)?;
+
+        Ok(Level {
+            name: header.name,
+            ball_speed: header.ball_speed,
+            layout: header.layout,
+            tile_map,
+        })
+    }
+
+    // A fresh copy of the level's tiles laid out to fit `playfield`
+    pub fn build_tile_map(&self, playfield: Rect) -> TileMap {
+        let mut tile_map = self.tile_map.clone();
+        tile_map.apply_layout(&self.layout, playfield);
+        tile_map
+    }
+}
+
+// Every level in the order they are played
+pub fn campaign() -> Vec<Level> {
+    vec![
+        image_level(
+            "Creeper",
+            270.0,
+            include_bytes!("../../assets/levels/creeper.png"),
+            &ImageLevelOptions {
+                layout: TileLayout {
+                    top_margin: 10.0,
+                    ..TileLayout::default()
+                },
+                ..ImageLevelOptions::default()
+            },
+        ),
+        text_level(
+            "rainbow.txt",
+            include_str!("../../assets/levels/rainbow.txt"),
+        ),
+        text_level(
+            "stripes.txt",
+            include_str!("../../assets/levels/stripes.txt"),
+        ),
+        text_level("vault.txt", include_str!("../../assets/levels/vault.txt")),
+    ]
+}
+
+// Built-in levels ship with the game, so one that doesn't load is a bug
+fn text_level(file: &str, source: &str) -> Level {
+    Level::from_level_str(source).unwrap_or_else(|error| panic!("{file}: {error}"))
+}
+
+fn image_level(name: &str, ball_speed: f32, png: &[u8], options: &ImageLevelOptions) -> Level {
+    let image = Image::from_file_with_format(png, Some(ImageFormat::Png))
+        .unwrap_or_else(|error| panic!("{name}: {error}"));
+
+    Level {
+        name: name.to_string(),
+        ball_speed,
+        layout: options.layout.clone(),
+        tile_map: TileMap::from_image(&image, options),
+    }
+}
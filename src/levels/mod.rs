@@ -0,0 +1 @@
+pub mod levels_class;
\ No newline at end of file
@@ -1,6 +1,7 @@
 use macroquad::prelude::*;
 
-const GAP_WIDTH: f32 = 10.0;
+use crate::arena::arena_class::Arena;
+
 // Your custom vector struct
 #[derive(Debug, Copy, Clone)]
 pub struct SimpleVec2 {
@@ -14,6 +15,7 @@ impl SimpleVec2 {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u8)]
 pub enum PlayerState {
     Moving = 0,
@@ -21,8 +23,26 @@ pub enum PlayerState {
     NotMoving = 2,
 }
 
+// Range of angles, measured from straight up in radians, that the ball can leave the
+// paddle at. Hits at the centre leave at `min`, hits at either end at `max`
+#[derive(Debug, Clone, Copy)]
+pub struct BounceAngles {
+    pub min: f32,
+    pub max: f32,
+}
+
+impl Default for BounceAngles {
+    fn default() -> Self {
+        BounceAngles {
+            min: 10f32.to_radians(),
+            max: 60f32.to_radians(),
+        }
+    }
+}
+
 pub struct Player {
     pub position_vector: Vec2,
+    pub previous_position: Vec2,
     pub movement_speed: f32,
     pub jump_height: f32,
     pub gravity: f32,
@@ -30,9 +50,23 @@ pub struct Player {
     pub is_grounded: bool,
     pub player_height: f32,
     pub player_width: f32,
+    pub bounce_angles: BounceAngles,
+    pub state: PlayerState,
+    // Current paddle velocity, in pixels per second
+    pub velocity: Vec2,
+    // Deceleration while no direction is held, in pixels per second squared
+    pub friction: f32,
+    // Height of the floor the paddle rests on and lands back onto after a jump
+    pub ground_y: f32,
+    // Share of the paddle's horizontal velocity added to the ball on contact ("english")
+    pub english_transfer: f32,
+    // Spin (radians per second of curve) given to the ball per pixel/second of paddle
+    // velocity, 0 turns curving balls off
+    pub spin_transfer: f32,
 }
 
 pub trait PlayerLogic {
+    #[allow(clippy::too_many_arguments)]
     fn new(
         initial_position: Vec2,
         movement_speed: f32,
@@ -44,13 +78,15 @@ pub trait PlayerLogic {
         player_width: f32,
     ) -> Self;
 
-    fn control_rectangle(&mut self, dt: f32, speed: Option<&f32>, screen_width: f32);
+    fn control_rectangle(&mut self, dt: f32, speed: Option<&f32>, arena: &Arena);
+
+    fn draw_player(&self, color: Color, alpha: f32);
 
-    fn draw_player(&self, color: Color);
+    fn apply_gravity(&mut self, dt: f32);
 
-    fn apply_gravity(&mut self);
+    fn reset_to(&mut self, position: Vec2);
 
-    fn get_player_rect(&mut self) -> Rect;
+    fn get_player_rect(&self) -> Rect;
 }
 
 impl PlayerLogic for Player {
@@ -66,6 +102,7 @@ impl PlayerLogic for Player {
     ) -> Self {
         Player {
             position_vector: initial_position,
+            previous_position: initial_position,
             movement_speed,
             jump_height,
             gravity,
@@ -73,41 +110,115 @@ impl PlayerLogic for Player {
             is_grounded,
             player_height,
             player_width,
+            bounce_angles: BounceAngles::default(),
+            state: PlayerState::NotMoving,
+            velocity: Vec2::ZERO,
+            friction: 2000.0,
+            ground_y: initial_position.y,
+            english_transfer: 0.3,
+            spin_transfer: 0.004,
         }
     }
 
-    fn control_rectangle(&mut self, dt: f32, speed: Option<&f32>, screen_width: f32) {
-        let actual_speed = *speed.unwrap_or(&self.movement_speed);
+    // Accelerate towards the held direction (up to `speed`, or the movement speed),
+    // slow down with friction when nothing is held, and jump off the ground
+    fn control_rectangle(&mut self, dt: f32, speed: Option<&f32>, arena: &Arena) {
+        let top_speed = *speed.unwrap_or(&self.movement_speed);
 
+        let mut direction = 0.0;
         if is_key_down(KeyCode::Right) {
-            self.position_vector.x += actual_speed * dt;
+            direction += 1.0;
         }
         if is_key_down(KeyCode::Left) {
-            self.position_vector.x -= actual_speed * dt;
+            direction -= 1.0;
+        }
+
+        if direction != 0.0 {
+            self.velocity.x += direction * self.acceleration * dt;
+            self.velocity.x = self.velocity.x.clamp(-top_speed, top_speed);
+        } else {
+            // Friction brings the paddle to a stop without pushing it back the other way
+            let slowdown = self.friction * dt;
+            if self.velocity.x.abs() <= slowdown {
+                self.velocity.x = 0.0;
+            } else {
+                self.velocity.x -= slowdown * self.velocity.x.signum();
+            }
+        }
+
+        // Launch upwards fast enough for gravity to stop the paddle at `jump_height`
+        if self.is_grounded && is_key_down(KeyCode::Up) {
+            self.velocity.y = -(2.0 * self.gravity * self.jump_height).sqrt();
+            self.is_grounded = false;
         }
 
-        // Clamp position to stay inside bounds [0, screen_width - player_width]
-        self.position_vector.x = self
-            .position_vector
-            .x
-            .clamp(GAP_WIDTH, screen_width - GAP_WIDTH);
+        self.apply_gravity(dt);
+        self.position_vector += self.velocity * dt;
+
+        // Land back on the floor
+        if self.position_vector.y >= self.ground_y {
+            self.position_vector.y = self.ground_y;
+            self.velocity.y = 0.0;
+            self.is_grounded = true;
+        }
+
+        // Keep the paddle inside the arena
+        let (min_x, max_x) = arena.paddle_range(self.player_width);
+        let clamped_x = self.position_vector.x.clamp(min_x, max_x);
+        if clamped_x != self.position_vector.x {
+            self.position_vector.x = clamped_x;
+            self.velocity.x = 0.0;
+        }
+
+        self.state = if !self.is_grounded {
+            PlayerState::Jumping
+        } else if self.velocity.x != 0.0 {
+            PlayerState::Moving
+        } else {
+            PlayerState::NotMoving
+        };
     }
 
-    fn draw_player(&self, color: Color) {
+    fn draw_player(&self, color: Color, alpha: f32) {
+        let position = self.previous_position.lerp(self.position_vector, alpha);
+
+        // Shadow on the floor while airborne, so it's clear where the paddle will land
+        if self.state == PlayerState::Jumping {
+            draw_rectangle(
+                position.x,
+                self.ground_y + self.player_height - 4.0,
+                self.player_width,
+                4.0,
+                Color::new(0.0, 0.0, 0.0, 0.25),
+            );
+        }
+
         draw_rectangle(
-            self.position_vector.x,
-            self.position_vector.y,
+            position.x,
+            position.y,
             self.player_width,
             self.player_height,
             color,
         );
     }
 
-    fn apply_gravity(&mut self) {
-        self.position_vector.y += self.gravity;
+    fn apply_gravity(&mut self, dt: f32) {
+        if !self.is_grounded {
+            self.velocity.y += self.gravity * dt;
+        }
+    }
+
+    // Put the paddle back on the floor at `position`, standing still
+    fn reset_to(&mut self, position: Vec2) {
+        self.position_vector = position;
+        self.previous_position = position;
+        self.velocity = Vec2::ZERO;
+        self.ground_y = position.y;
+        self.is_grounded = true;
+        self.state = PlayerState::NotMoving;
     }
 
-    fn get_player_rect(&mut self) -> Rect {
+    fn get_player_rect(&self) -> Rect {
         Rect::new(
             self.position_vector.x,
             self.position_vector.y,
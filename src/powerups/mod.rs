@@ -0,0 +1 @@
+pub mod powerups_class;
\ No newline at end of file
@@ -0,0 +1,271 @@
+use macroquad::prelude::*;
+
+use crate::arena::arena_class::Arena;
+use crate::tiles::tiles_class::{TileHit, TileKind};
+
+// Chance that a destroyed tile drops a capsule, power-up carriers always drop one
+const DROP_CHANCE: f32 = 0.15;
+
+const CAPSULE_WIDTH: f32 = 40.0;
+const CAPSULE_HEIGHT: f32 = 16.0;
+// Pixels per second
+const CAPSULE_FALL_SPEED: f32 = 150.0;
+
+// How long timed effects last, in seconds
+const EFFECT_DURATION: f32 = 12.0;
+// How long the label for an instant effect stays up, in seconds
+const PICKUP_LABEL_DURATION: f32 = 1.5;
+
+// How much each effect scales the paddle's width or the ball's speed
+const WIDEN_SCALE: f32 = 1.5;
+const SHRINK_SCALE: f32 = 0.6;
+const SLOW_BALL_SCALE: f32 = 0.7;
+const FAST_BALL_SCALE: f32 = 1.35;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PowerUpKind {
+    Widen,
+    Shrink,
+    SlowBall,
+    FastBall,
+    MultiBall,
+    Sticky,
+    ExtraLife,
+    Laser,
+}
+
+impl PowerUpKind {
+    pub const ALL: [PowerUpKind; 8] = [
+        PowerUpKind::Widen,
+        PowerUpKind::Shrink,
+        PowerUpKind::SlowBall,
+        PowerUpKind::FastBall,
+        PowerUpKind::MultiBall,
+        PowerUpKind::Sticky,
+        PowerUpKind::ExtraLife,
+        PowerUpKind::Laser,
+    ];
+
+    pub fn random() -> Self {
+        PowerUpKind::ALL[rand::gen_range(0, PowerUpKind::ALL.len())]
+    }
+
+    // How long the effect lasts, None for effects that happen once when caught
+    pub fn duration(self) -> Option<f32> {
+        match self {
+            PowerUpKind::MultiBall | PowerUpKind::ExtraLife => None,
+            _ => Some(EFFECT_DURATION),
+        }
+    }
+
+    // The effect this one replaces, so widen and shrink (or slow and fast) never stack
+    fn opposite(self) -> Option<PowerUpKind> {
+        match self {
+            PowerUpKind::Widen => Some(PowerUpKind::Shrink),
+            PowerUpKind::Shrink => Some(PowerUpKind::Widen),
+            PowerUpKind::SlowBall => Some(PowerUpKind::FastBall),
+            PowerUpKind::FastBall => Some(PowerUpKind::SlowBall),
+            _ => None,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            PowerUpKind::Widen => "Wide",
+            PowerUpKind::Shrink => "Narrow",
+            PowerUpKind::SlowBall => "Slow",
+            PowerUpKind::FastBall => "Fast",
+            PowerUpKind::MultiBall => "Multi",
+            PowerUpKind::Sticky => "Sticky",
+            PowerUpKind::ExtraLife => "1UP",
+            PowerUpKind::Laser => "Laser",
+        }
+    }
+
+    pub fn color(self) -> Color {
+        match self {
+            PowerUpKind::Widen => BLUE,
+            PowerUpKind::Shrink => MAROON,
+            PowerUpKind::SlowBall => SKYBLUE,
+            PowerUpKind::FastBall => ORANGE,
+            PowerUpKind::MultiBall => VIOLET,
+            PowerUpKind::Sticky => DARKGREEN,
+            PowerUpKind::ExtraLife => PINK,
+            PowerUpKind::Laser => RED,
+        }
+    }
+}
+
+// A power-up falling from a destroyed tile, caught by touching the paddle
+pub struct Capsule {
+    pub position: Vec2,
+    pub kind: PowerUpKind,
+}
+
+impl Capsule {
+    pub fn get_rect(&self) -> Rect {
+        Rect::new(
+            self.position.x - CAPSULE_WIDTH / 2.0,
+            self.position.y - CAPSULE_HEIGHT / 2.0,
+            CAPSULE_WIDTH,
+            CAPSULE_HEIGHT,
+        )
+    }
+
+    pub fn draw(&self) {
+        let rect = self.get_rect();
+        draw_rectangle(rect.x, rect.y, rect.w, rect.h, self.kind.color());
+        draw_rectangle_lines(rect.x, rect.y, rect.w, rect.h, 2.0, WHITE);
+
+        let dims = measure_text(self.kind.label(), None, 14, 1.0);
+        draw_text(
+            self.kind.label(),
+            self.position.x - dims.width / 2.0,
+            self.position.y + dims.offset_y / 2.0,
+            14.0,
+            WHITE,
+        );
+    }
+}
+
+// A caught timed effect and how long it has left
+pub struct ActiveEffect {
+    pub kind: PowerUpKind,
+    pub remaining: f32,
+}
+
+// Falling capsules plus the timed effects currently running
+#[derive(Default)]
+pub struct PowerUps {
+    pub capsules: Vec<Capsule>,
+    pub effects: Vec<ActiveEffect>,
+    // Instant effects just caught, `remaining` is how long their label stays up
+    pub pickups: Vec<ActiveEffect>,
+}
+
+impl PowerUps {
+    pub fn new() -> Self {
+        PowerUps::default()
+    }
+
+    // Drop everything, used whenever a serve starts
+    pub fn clear(&mut self) {
+        self.capsules.clear();
+        self.effects.clear();
+        self.pickups.clear();
+    }
+
+    // Maybe drop a capsule from a tile that was hit, `position` is where it starts falling
+    pub fn spawn_from(&mut self, hit: &TileHit, position: Vec2) {
+        if !hit.destroyed {
+            return;
+        }
+
+        let drops = hit.kind == TileKind::PowerUpCarrier || rand::gen_range(0.0, 1.0) < DROP_CHANCE;
+        if drops {
+            self.capsules.push(Capsule {
+                position,
+                kind: PowerUpKind::random(),
+            });
+        }
+    }
+
+    // Move capsules and run down effect timers. Returns the kinds of every capsule the
+    // paddle caught this step, capsules that fall out of the arena are lost
+    pub fn update(&mut self, dt: f32, paddle_rect: Rect, arena: &Arena) -> Vec<PowerUpKind> {
+        for effect in self.effects.iter_mut().chain(&mut self.pickups) {
+            effect.remaining -= dt;
+        }
+        self.effects.retain(|effect| effect.remaining > 0.0);
+        self.pickups.retain(|pickup| pickup.remaining > 0.0);
+
+        let mut caught = vec![];
+        self.capsules.retain_mut(|capsule| {
+            capsule.position.y += CAPSULE_FALL_SPEED * dt;
+
+            if capsule.get_rect().overlaps(&paddle_rect) {
+                caught.push(capsule.kind);
+                return false;
+            }
+            !arena.is_outside(capsule.get_rect())
+        });
+
+        caught
+    }
+
+    // Start a timed effect, or restart its timer if it is already running. Instant
+    // effects only put up their label for a moment, the game applies them itself
+    pub fn activate(&mut self, kind: PowerUpKind) {
+        let Some(duration) = kind.duration() else {
+            self.pickups.retain(|pickup| pickup.kind != kind);
+            self.pickups.push(ActiveEffect {
+                kind,
+                remaining: PICKUP_LABEL_DURATION,
+            });
+            return;
+        };
+
+        if let Some(opposite) = kind.opposite() {
+            self.effects.retain(|effect| effect.kind != opposite);
+        }
+        match self.effects.iter_mut().find(|effect| effect.kind == kind) {
+            Some(effect) => effect.remaining = duration,
+            None => self.effects.push(ActiveEffect {
+                kind,
+                remaining: duration,
+            }),
+        }
+    }
+
+    pub fn is_active(&self, kind: PowerUpKind) -> bool {
+        self.effects.iter().any(|effect| effect.kind == kind)
+    }
+
+    // Paddle width as a multiple of its normal width
+    pub fn paddle_width_scale(&self) -> f32 {
+        if self.is_active(PowerUpKind::Widen) {
+            WIDEN_SCALE
+        } else if self.is_active(PowerUpKind::Shrink) {
+            SHRINK_SCALE
+        } else {
+            1.0
+        }
+    }
+
+    // Ball speed as a multiple of the level's speed
+    pub fn ball_speed_scale(&self) -> f32 {
+        if self.is_active(PowerUpKind::SlowBall) {
+            SLOW_BALL_SCALE
+        } else if self.is_active(PowerUpKind::FastBall) {
+            FAST_BALL_SCALE
+        } else {
+            1.0
+        }
+    }
+
+    pub fn draw(&self) {
+        for capsule in &self.capsules {
+            capsule.draw();
+        }
+    }
+
+    // One label per running effect with the seconds it has left, from (x, y) rightwards,
+    // then the labels of instant effects just caught, fading out
+    pub fn draw_indicators(&self, x: f32, y: f32) {
+        let mut x = x;
+        for effect in &self.effects {
+            let text = format!("{} {:.0}s", effect.kind.label(), effect.remaining.ceil());
+            draw_text(&text, x, y, 24.0, effect.kind.color());
+            x += measure_text(&text, None, 24, 1.0).width + 20.0;
+        }
+        for pickup in &self.pickups {
+            let text = format!("{}!", pickup.kind.label());
+            let color = Color {
+                a: pickup.remaining / PICKUP_LABEL_DURATION,
+                ..pickup.kind.color()
+            };
+            draw_text(&text, x, y, 24.0, color);
+            x += measure_text(&text, None, 24, 1.0).width + 20.0;
+        }
+    }
+}
@@ -0,0 +1 @@
+pub mod score_class;
\ No newline at end of file
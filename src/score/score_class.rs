@@ -0,0 +1,125 @@
+use macroquad::prelude::*;
+
+use crate::tiles::tiles_class::TileHit;
+
+// Highest multiplier a combo can reach
+const MAX_COMBO_MULTIPLIER: u32 = 8;
+
+// Base points per tile colour, anything not listed is worth the default
+const TILE_POINTS: [(Color, u32); 3] = [(BLACK, 50), (GREEN, 20), (DARKGREEN, 10)];
+const DEFAULT_TILE_POINTS: u32 = 10;
+// Points for chipping a hit point off a tough tile that stays standing
+const DAMAGE_POINTS: u32 = 5;
+
+// How long the "+points" text stays on screen after a hit
+const POPUP_LIFETIME: f32 = 0.8;
+
+pub fn tile_points(color: Color) -> u32 {
+    TILE_POINTS
+        .iter()
+        .find(|(tile_color, _)| *tile_color == color)
+        .map_or(DEFAULT_TILE_POINTS, |&(_, points)| points)
+}
+
+// Damaging a tile gives a small fixed amount, destroying it gives its colour's points
+// scaled by how many hits it took
+pub fn hit_points(hit: &TileHit) -> u32 {
+    if hit.destroyed {
+        tile_points(hit.color) * hit.max_hit_points
+    } else {
+        DAMAGE_POINTS
+    }
+}
+
+// Floating text showing the points a single hit was worth
+struct ScorePopup {
+    position: Vec2,
+    points: u32,
+    age: f32,
+    destroyed: bool,
+}
+
+// Running score plus the combo of tiles hit since the ball last touched the paddle
+#[derive(Default)]
+pub struct Score {
+    pub points: u32,
+    pub combo: u32,
+    popups: Vec<ScorePopup>,
+}
+
+impl Score {
+    pub fn new() -> Self {
+        Score::default()
+    }
+
+    pub fn reset(&mut self) {
+        self.points = 0;
+        self.combo = 0;
+        self.popups.clear();
+    }
+
+    // Every consecutive tile hit adds one to the multiplier, up to the cap
+    pub fn multiplier(&self) -> u32 {
+        self.combo.clamp(1, MAX_COMBO_MULTIPLIER)
+    }
+
+    // Scores a damaged or destroyed tile, `position` is where its popup text appears
+    pub fn register_hit(&mut self, hit: &TileHit, position: Vec2) {
+        self.combo += 1;
+        let gained = hit_points(hit) * self.multiplier();
+        self.points += gained;
+
+        self.popups.push(ScorePopup {
+            position,
+            points: gained,
+            age: 0.0,
+            destroyed: hit.destroyed,
+        });
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        for popup in &mut self.popups {
+            popup.age += dt;
+            popup.position.y -= 40.0 * dt;
+        }
+        self.popups.retain(|popup| popup.age < POPUP_LIFETIME);
+    }
+
+    // Called whenever the ball returns to the paddle or is lost
+    pub fn break_combo(&mut self) {
+        self.combo = 0;
+    }
+
+    pub fn draw_popups(&self) {
+        for popup in &self.popups {
+            let alpha = 1.0 - popup.age / POPUP_LIFETIME;
+            // Chipped tiles get a smaller grey popup than destroyed ones
+            let (size, color) = if popup.destroyed {
+                (24.0, Color::new(1.0, 0.5, 0.0, alpha))
+            } else {
+                (18.0, Color::new(0.4, 0.4, 0.4, alpha))
+            };
+            draw_text(
+                &format!("+{}", popup.points),
+                popup.position.x,
+                popup.position.y,
+                size,
+                color,
+            );
+        }
+    }
+
+    pub fn draw(&self, x: f32, y: f32) {
+        draw_text(&format!("Score: {}", self.points), x, y, 30.0, BLACK);
+
+        if self.combo > 1 {
+            draw_text(
+                &format!("Combo x{}", self.multiplier()),
+                x,
+                y + 30.0,
+                26.0,
+                ORANGE,
+            );
+        }
+    }
+}
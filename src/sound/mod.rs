@@ -0,0 +1 @@
+pub mod sound_class;
\ No newline at end of file
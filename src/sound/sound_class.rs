@@ -0,0 +1,55 @@
+#[cfg(any(feature = "sound", target_arch = "wasm32"))]
+use macroquad::audio::{Sound, load_sound_from_bytes, play_sound_once};
+
+use crate::tiles::tiles_class::TileHit;
+
+// Sound effects for tiles being chipped and destroyed. They only play in web builds or
+// when the game is built with the `sound` feature, otherwise every sound is skipped
+pub struct TileSounds {
+    #[cfg(any(feature = "sound", target_arch = "wasm32"))]
+    damaged: Sound,
+    #[cfg(any(feature = "sound", target_arch = "wasm32"))]
+    destroyed: Sound,
+}
+
+impl TileSounds {
+    #[cfg(any(feature = "sound", target_arch = "wasm32"))]
+    pub async fn load() -> Self {
+        let load = async |name: &str, bytes: &[u8]| {
+            load_sound_from_bytes(bytes)
+                .await
+                .unwrap_or_else(|error| panic!("{name}: {error}"))
+        };
+
+        TileSounds {
+            damaged: load(
+                "tile_damaged.wav",
+                include_bytes!("../../assets/sounds/tile_damaged.wav"),
+            )
+            .await,
+            destroyed: load(
+                "tile_destroyed.wav",
+                include_bytes!("../../assets/sounds/tile_destroyed.wav"),
+            )
+            .await,
+        }
+    }
+
+    #[cfg(not(any(feature = "sound", target_arch = "wasm32")))]
+    pub async fn load() -> Self {
+        TileSounds {}
+    }
+
+    // A short click for a tile that only lost a hit point, a crunch for one that broke
+    #[cfg(any(feature = "sound", target_arch = "wasm32"))]
+    pub fn play_hit(&self, hit: &TileHit) {
+        if hit.destroyed {
+            play_sound_once(&self.destroyed);
+        } else {
+            play_sound_once(&self.damaged);
+        }
+    }
+
+    #[cfg(not(any(feature = "sound", target_arch = "wasm32")))]
+    pub fn play_hit(&self, _hit: &TileHit) {}
+}
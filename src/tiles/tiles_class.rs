@@ -1,14 +1,90 @@
+use std::fmt;
+
 use macroquad::prelude::*;
 
-use crate::ball::ball_class::Ball;
+use crate::arena::arena_class::Arena;
+use crate::collision::collision_class::{
+    SweepHit, circle_rect_contact, combine_hits, is_simultaneous, sweep_circle_rect,
+};
+
+// How much darker a tile gets once it has lost all but its last hit point
+const DAMAGE_DARKEN: f32 = 0.5;
+
+// Extra distance a ball is placed beyond the exit portal so it comes out clear of it
+const PORTAL_EXIT_MARGIN: f32 = 0.5;
+
+// How many cells out from an explosive tile its blast reaches, in every direction
+const EXPLOSION_RADIUS: usize = 1;
+// Time between an explosive being destroyed by a blast and it going off in turn
+const CHAIN_DELAY: f32 = 0.15;
+// How long the flash of a blast stays on screen
+const BLAST_LIFETIME: f32 = 0.25;
+
+// What a tile does when the ball hits it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TileKind {
+    // Breaks in one hit
+    Normal,
+    // Takes this many hits to break
+    MultiHit(u32),
+    // Never breaks, and doesn't need to be cleared to finish the level
+    Indestructible,
+    // Breaks in one hit and damages the tiles around it
+    Explosive,
+    // Breaks in one hit and always drops a power-up
+    PowerUpCarrier,
+    // Sends the ball out of the other portal on the same channel
+    Portal(u8),
+}
+
+impl TileKind {
+    pub fn hit_points(self) -> u32 {
+        match self {
+            TileKind::MultiHit(hit_points) => hit_points.max(1),
+            _ => 1,
+        }
+    }
+
+    // Whether the tile can be broken, and so has to be for the level to be cleared
+    pub fn is_breakable(self) -> bool {
+        !matches!(self, TileKind::Indestructible | TileKind::Portal(_))
+    }
+}
+
+// What a pattern cell turns into: the tile's colour and kind
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TileSpec {
+    pub color: Color,
+    pub kind: TileKind,
+}
+
+impl TileSpec {
+    pub fn new(color: Color, kind: TileKind) -> Self {
+        TileSpec { color, kind }
+    }
+}
+
+// Rows of pattern cells, None is an empty cell
+pub type TilePattern = Vec<Vec<Option<TileSpec>>>;
+
+// A plain colour in a pattern is a normal one-hit tile
+impl From<Color> for TileSpec {
+    fn from(color: Color) -> Self {
+        TileSpec::new(color, TileKind::Normal)
+    }
+}
 
 // Single tile definition
+#[derive(Clone)]
 pub struct Tile {
     pub position: Vec2,
     pub size: Vec2,
     pub color: Color,
     pub is_solid: bool,
     pub destroyed: bool,
+    pub hit_points: u32,
+    pub max_hit_points: u32,
+    pub kind: TileKind,
 }
 
 impl Tile {
@@ -19,18 +95,99 @@ impl Tile {
             color,
             is_solid,
             destroyed: false,
+            hit_points: 1,
+            max_hit_points: 1,
+            kind: TileKind::Normal,
         }
     }
 
+    pub fn with_kind(self, kind: TileKind) -> Self {
+        Tile {
+            kind,
+            hit_points: kind.hit_points(),
+            max_hit_points: kind.hit_points(),
+            ..self
+        }
+    }
+
+    // Fraction of the tile's hit points already knocked off (0 when untouched)
+    pub fn damage(&self) -> f32 {
+        1.0 - self.hit_points as f32 / self.max_hit_points as f32
+    }
+
     pub fn draw(&self) {
-        if !self.destroyed {
-            draw_rectangle(
-                self.position.x,
-                self.position.y,
-                self.size.x,
-                self.size.y,
-                self.color,
-            );
+        if self.destroyed {
+            return;
+        }
+
+        // Damaged tiles darken and show a crack for every hit they have taken
+        let shade = 1.0 - self.damage() * DAMAGE_DARKEN;
+        let color = Color::new(
+            self.color.r * shade,
+            self.color.g * shade,
+            self.color.b * shade,
+            self.color.a,
+        );
+        draw_rectangle(
+            self.position.x,
+            self.position.y,
+            self.size.x,
+            self.size.y,
+            color,
+        );
+
+        let center = self.position + self.size / 2.0;
+        let rect = self.get_rect();
+        match self.kind {
+            TileKind::Normal | TileKind::MultiHit(_) => {}
+            TileKind::Indestructible => {
+                draw_rectangle_lines(rect.x, rect.y, rect.w, rect.h, 4.0, DARKGRAY);
+            }
+            TileKind::Explosive => {
+                let size = self.size.y * 0.3;
+                draw_line(
+                    center.x - size,
+                    center.y - size,
+                    center.x + size,
+                    center.y + size,
+                    3.0,
+                    BLACK,
+                );
+                draw_line(
+                    center.x - size,
+                    center.y + size,
+                    center.x + size,
+                    center.y - size,
+                    3.0,
+                    BLACK,
+                );
+            }
+            TileKind::PowerUpCarrier => {
+                draw_circle(center.x, center.y, self.size.y * 0.25, WHITE);
+            }
+            TileKind::Portal(_) => {
+                draw_ellipse_lines(
+                    center.x,
+                    center.y,
+                    rect.w / 2.0 - 3.0,
+                    rect.h / 2.0 - 3.0,
+                    0.0,
+                    3.0,
+                    WHITE,
+                );
+            }
+        }
+
+        let cracks = self.max_hit_points - self.hit_points;
+        for crack in 0..cracks {
+            // Spread the cracks around the tile so each new one is visible
+            let angle = crack as f32 * 2.4 + 0.6;
+            let end = center
+                + Vec2::new(
+                    angle.cos() * self.size.x / 2.0,
+                    angle.sin() * self.size.y / 2.0,
+                );
+            draw_line(center.x, center.y, end.x, end.y, 2.0, BLACK);
         }
     }
 
@@ -39,17 +196,58 @@ impl Tile {
     }
 }
 
+// A tile the ball damaged or destroyed, reported back so the game can score it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TileHit {
+    pub row: usize,
+    pub col: usize,
+    pub color: Color,
+    pub max_hit_points: u32,
+    pub kind: TileKind,
+    // False when the tile only lost a hit point and is still standing
+    pub destroyed: bool,
+}
+
+// An explosive tile destroyed by a blast, waiting for its fuse to burn down
+#[derive(Clone)]
+struct PendingExplosion {
+    row: usize,
+    col: usize,
+    fuse: f32,
+}
+
+// The fading flash over the area a blast covered
+#[derive(Clone)]
+struct Blast {
+    area: Rect,
+    age: f32,
+}
+
+// Result of sweeping a ball through the map: when and how it bounces, plus every
+// tile (row, col) it touches at that moment
+pub struct TileSweep {
+    pub hit: SweepHit,
+    pub tiles: Vec<(usize, usize)>,
+}
+
 // The complete tilemap (grid of tiles with gaps/space)
+#[derive(Clone)]
 pub struct TileMap {
     pub tiles: Vec<Vec<Tile>>,
     pub rows: usize,
     pub cols: usize,
     pub tile_size: Vec2,
     pub gap: f32,
+    // Top-left corner of the tile at row 0, col 0
+    pub origin: Vec2,
+    // Chained explosions still to go off, and the blasts being shown
+    pending_explosions: Vec<PendingExplosion>,
+    blasts: Vec<Blast>,
 }
 
 impl TileMap {
     /// Covers only top 2/3 of the screen, with rectangular tiles and gaps in grid
+    #[allow(dead_code)]
     pub fn new(
         screen_width: f32,
         screen_height: f32,
@@ -87,60 +285,293 @@ impl TileMap {
             cols,
             tile_size: Vec2::new(tile_width, tile_height),
             gap,
+            origin: Vec2::splat(gap / 2.0),
+            pending_explosions: vec![],
+            blasts: vec![],
         }
     }
 
+    // True once every solid tile that can be broken has been destroyed
+    pub fn is_cleared(&self) -> bool {
+        self.tiles
+            .iter()
+            .flatten()
+            .all(|tile| tile.destroyed || !tile.is_solid || !tile.kind.is_breakable())
+    }
+
     pub fn draw(&self) {
         for row in &self.tiles {
             for tile in row {
                 tile.draw();
             }
         }
+
+        // Explosives waiting to go off flash between yellow and red as the fuse burns
+        for explosion in &self.pending_explosions {
+            let rect = self.tiles[explosion.row][explosion.col].get_rect();
+            let flash = (explosion.fuse / CHAIN_DELAY * 3.0).fract();
+            let color = Color::new(1.0, flash, 0.0, 1.0);
+            draw_rectangle(rect.x, rect.y, rect.w, rect.h, color);
+        }
+
+        for blast in &self.blasts {
+            let alpha = 0.6 * (1.0 - blast.age / BLAST_LIFETIME);
+            let area = blast.area;
+            draw_rectangle(
+                area.x,
+                area.y,
+                area.w,
+                area.h,
+                Color::new(1.0, 0.6, 0.0, alpha),
+            );
+        }
     }
 
-    // Destroy tile on collision with ball (call every frame in main loop)
-    pub fn handle_ball_collision(&mut self, ball: &mut Ball) {
-        for row in &mut self.tiles {
-            for tile in row {
-                if !tile.destroyed && tile.is_solid {
-                    let tile_rect = tile.get_rect();
-                    // Ball-rectangle collision (circle-to-rect)
-                    let closest_x = ball
-                        .position
-                        .x
-                        .clamp(tile_rect.x, tile_rect.x + tile_rect.w);
-                    let closest_y = ball
-                        .position
-                        .y
-                        .clamp(tile_rect.y, tile_rect.y + tile_rect.h);
-
-                    let dist_x = ball.position.x - closest_x;
-                    let dist_y = ball.position.y - closest_y;
-                    let dist_sq = dist_x * dist_x + dist_y * dist_y;
-
-                    if dist_sq < ball.radius * ball.radius {
-                        // Mark the tile as destroyed
-                        tile.destroyed = true;
-                        // Bounce the ball (invert Y)
-                        ball.velocity.y = -ball.velocity.y;
-
-                        // Push ball out of the tile to avoid sticking
-                        let dist = dist_sq.sqrt();
-                        let penetration = ball.radius - dist;
-                        if dist != 0.0 {
-                            let normal = vec2(dist_x / dist, dist_y / dist);
-                            ball.position += normal * penetration;
-                        }
-                        // Optionally: break on first collision per tick
-                        break;
-                    }
+    // Drop chained explosions that haven't gone off yet, along with the blasts on show
+    pub fn clear_explosions(&mut self) {
+        self.pending_explosions.clear();
+        self.blasts.clear();
+    }
+
+    // Advance chained explosions, returning every tile damaged by the ones that went off
+    pub fn update(&mut self, dt: f32) -> Vec<TileHit> {
+        for blast in &mut self.blasts {
+            blast.age += dt;
+        }
+        self.blasts.retain(|blast| blast.age < BLAST_LIFETIME);
+
+        for explosion in &mut self.pending_explosions {
+            explosion.fuse -= dt;
+        }
+        let (due, pending) = std::mem::take(&mut self.pending_explosions)
+            .into_iter()
+            .partition::<Vec<_>, _>(|explosion| explosion.fuse <= 0.0);
+        self.pending_explosions = pending;
+
+        due.into_iter()
+            .flat_map(|explosion| self.detonate(explosion.row, explosion.col))
+            .collect()
+    }
+
+    // Distance from one tile to the next along each axis
+    fn pitch(&self) -> Vec2 {
+        self.tile_size + Vec2::splat(self.gap)
+    }
+
+    // Grid cells (row, col) whose tile, plus the gap after it, overlaps `area`.
+    // Lets collision look at only the handful of tiles near a ball instead of all of them
+    pub fn cells_in_rect(&self, area: Rect) -> impl Iterator<Item = (usize, usize)> + use<> {
+        let pitch = self.pitch();
+        let first = ((area.point() - self.origin) / pitch).floor();
+        let last = ((area.point() + area.size() - self.origin) / pitch).floor();
+
+        // Clamp to the grid, an area entirely outside it gives empty ranges
+        let span = |first: f32, last: f32, count: usize| {
+            let start = first.max(0.0) as usize;
+            let end = if last < 0.0 {
+                0
+            } else {
+                (last as usize + 1).min(count)
+            };
+            start..end
+        };
+
+        let rows = span(first.y, last.y, self.rows);
+        let cols = span(first.x, last.x, self.cols);
+        rows.flat_map(move |row| cols.clone().map(move |col| (row, col)))
+    }
+
+    // Earliest tile(s) a ball moving from `start` by `motion` would run into.
+    // Tiles reached at the same moment are all reported, with their normals blended
+    pub fn sweep_ball(&self, start: Vec2, motion: Vec2, radius: f32) -> Option<TileSweep> {
+        let mut hits = vec![];
+
+        // Broadphase: only tiles in cells covered by the ball's path
+        let end = start + motion;
+        let path = Rect::new(
+            start.x.min(end.x) - radius,
+            start.y.min(end.y) - radius,
+            motion.x.abs() + radius * 2.0,
+            motion.y.abs() + radius * 2.0,
+        );
+
+        for (row_idx, col_idx) in self.cells_in_rect(path) {
+            let Some(tile) = self.tiles[row_idx].get(col_idx) else {
+                continue;
+            };
+            if tile.destroyed || !tile.is_solid {
+                continue;
+            }
+
+            if let Some(hit) = sweep_circle_rect(start, motion, radius, tile.get_rect()) {
+                hits.push((hit, row_idx, col_idx));
+            }
+        }
+
+        let sweep_hits: Vec<SweepHit> = hits.iter().map(|&(hit, _, _)| hit).collect();
+        let combined = combine_hits(&sweep_hits)?;
+
+        let tiles = hits
+            .iter()
+            .filter(|(hit, _, _)| is_simultaneous(hit.time, combined.time))
+            .map(|&(_, row, col)| (row, col))
+            .collect();
+
+        Some(TileSweep {
+            hit: combined,
+            tiles,
+        })
+    }
+
+    // Apply a ball hit to a tile according to its kind, returning every tile that took
+    // damage because of it
+    pub fn hit_tile(&mut self, row: usize, col: usize) -> Vec<TileHit> {
+        match self.tiles[row][col].kind {
+            TileKind::Indestructible | TileKind::Portal(_) => vec![],
+            TileKind::Explosive => {
+                let hit = self.damage_tile(row, col);
+                let mut hits = vec![hit];
+                if hit.destroyed {
+                    hits.extend(self.detonate(row, col));
+                }
+                hits
+            }
+            TileKind::Normal | TileKind::MultiHit(_) | TileKind::PowerUpCarrier => {
+                vec![self.damage_tile(row, col)]
+            }
+        }
+    }
+
+    // Knock a hit point off a tile, destroying it on the last one
+    fn damage_tile(&mut self, row: usize, col: usize) -> TileHit {
+        let tile = &mut self.tiles[row][col];
+        tile.hit_points = tile.hit_points.saturating_sub(1);
+        tile.destroyed = tile.hit_points == 0;
+
+        TileHit {
+            row,
+            col,
+            color: tile.color,
+            max_hit_points: tile.max_hit_points,
+            kind: tile.kind,
+            destroyed: tile.destroyed,
+        }
+    }
+
+    // Damage every breakable tile within the blast radius of the explosive at (row, col).
+    // Explosives destroyed by the blast are queued to go off after a short delay
+    fn detonate(&mut self, row: usize, col: usize) -> Vec<TileHit> {
+        let center = self.tiles[row][col].get_rect().center();
+        let reach = self.pitch() * (EXPLOSION_RADIUS as f32 + 0.5);
+        self.blasts.push(Blast {
+            area: Rect::new(
+                center.x - reach.x,
+                center.y - reach.y,
+                reach.x * 2.0,
+                reach.y * 2.0,
+            ),
+            age: 0.0,
+        });
+
+        let mut hits = vec![];
+
+        let rows = row.saturating_sub(EXPLOSION_RADIUS)..=row + EXPLOSION_RADIUS;
+        for neighbour_row in rows {
+            let cols = col.saturating_sub(EXPLOSION_RADIUS)..=col + EXPLOSION_RADIUS;
+            for neighbour_col in cols {
+                if (neighbour_row, neighbour_col) == (row, col) {
+                    continue;
+                }
+
+                let Some(tile) = self
+                    .tiles
+                    .get(neighbour_row)
+                    .and_then(|tiles| tiles.get(neighbour_col))
+                else {
+                    continue;
+                };
+                if tile.destroyed || !tile.is_solid || !tile.kind.is_breakable() {
+                    continue;
                 }
+
+                let hit = self.damage_tile(neighbour_row, neighbour_col);
+                if hit.destroyed && hit.kind == TileKind::Explosive {
+                    self.pending_explosions.push(PendingExplosion {
+                        row: neighbour_row,
+                        col: neighbour_col,
+                        fuse: CHAIN_DELAY,
+                    });
+                }
+                hits.push(hit);
             }
         }
+
+        hits
     }
 
-    pub fn from_pattern(
-        pattern: &[Vec<Option<Color>>],
+    // Where a ball entering the portal at (row, col) comes out: just past the other
+    // portal on the same channel, along its direction of travel. None if the tile isn't
+    // a portal, has no partner, or the ball would come out somewhere it can't be (outside
+    // the arena or inside something solid), in which case it behaves like a wall
+    pub fn portal_exit(
+        &self,
+        row: usize,
+        col: usize,
+        velocity: Vec2,
+        radius: f32,
+        arena: &Arena,
+    ) -> Option<Vec2> {
+        let TileKind::Portal(channel) = self.tiles[row][col].kind else {
+            return None;
+        };
+
+        let exit = self
+            .tiles
+            .iter()
+            .enumerate()
+            .find_map(|(exit_row, tiles)| {
+                tiles.iter().enumerate().find_map(|(exit_col, tile)| {
+                    let is_partner = tile.kind == TileKind::Portal(channel)
+                        && !tile.destroyed
+                        && (exit_row, exit_col) != (row, col);
+                    is_partner.then_some(tile)
+                })
+            })?;
+
+        // Distance along the direction of travel to clear the exit portal (grown by
+        // the ball's radius) from its centre
+        let direction = velocity.try_normalize()?;
+        let half_extent = exit.size / 2.0 + Vec2::splat(radius);
+        let clear_x = half_extent.x / direction.x.abs();
+        let clear_y = half_extent.y / direction.y.abs();
+
+        let rect = exit.get_rect();
+        let exit_point = rect.center() + direction * (clear_x.min(clear_y) + PORTAL_EXIT_MARGIN);
+
+        let has_room = arena.has_room_for(exit_point, radius) && self.is_clear(exit_point, radius);
+        has_room.then_some(exit_point)
+    }
+
+    // Whether a ball placed here would be clear of every solid tile
+    pub fn is_clear(&self, position: Vec2, radius: f32) -> bool {
+        let area = Rect::new(
+            position.x - radius,
+            position.y - radius,
+            radius * 2.0,
+            radius * 2.0,
+        );
+
+        self.cells_in_rect(area).all(|(row, col)| {
+            let tile = &self.tiles[row][col];
+            !tile.is_solid
+                || tile.destroyed
+                || circle_rect_contact(position, radius, tile.get_rect()).is_none()
+        })
+    }
+
+    // Cells can be plain colours or full `TileSpec`s with a tile kind
+    pub fn from_pattern<T: Into<TileSpec> + Copy>(
+        pattern: &[Vec<Option<T>>],
         tile_width: f32,
         tile_height: f32,
         gap: f32,
@@ -153,13 +584,15 @@ impl TileMap {
         for (row_idx, row) in pattern.iter().enumerate() {
             let mut row_tiles = vec![];
 
-            for (col_idx, &color_option) in row.iter().enumerate() {
+            for (col_idx, &cell) in row.iter().enumerate() {
                 let x = col_idx as f32 * (tile_width + gap) + gap / 2.0;
                 let y = row_idx as f32 * (tile_height + gap) + gap / 2.0;
 
-                // Create tile only if pattern cell is Some(color)
-                if let Some(color) = color_option {
-                    let tile = Tile::new(x, y, tile_width, tile_height, color, true);
+                // Create tile only if pattern cell is Some(spec)
+                if let Some(cell) = cell {
+                    let spec: TileSpec = cell.into();
+                    let tile = Tile::new(x, y, tile_width, tile_height, spec.color, true)
+                        .with_kind(spec.kind);
                     row_tiles.push(tile);
                 } else {
                     // Optional: Push a non-solid/destroyed tile or skip
@@ -180,6 +613,1031 @@ impl TileMap {
             cols,
             tile_size: Vec2::new(tile_width, tile_height),
             gap,
+            origin: Vec2::splat(gap / 2.0),
+            pending_explosions: vec![],
+            blasts: vec![],
         }
     }
+
+    // Build a map from pixel art, one tile per pixel (or per block of pixels when
+    // downsampling) in that pixel's colour. Transparent pixels are gaps
+    pub fn from_image(image: &Image, options: &ImageLevelOptions) -> Self {
+        let block = options.downsample.max(1) as usize;
+        let (width, height) = (image.width as usize, image.height as usize);
+        let pixels = image.get_image_data();
+
+        let pattern: TilePattern = (0..height.div_ceil(block))
+            .map(|row| {
+                (0..width.div_ceil(block))
+                    .map(|col| {
+                        let block_pixels =
+                            (row * block..((row + 1) * block).min(height)).flat_map(|y| {
+                                (col * block..((col + 1) * block).min(width))
+                                    .map(move |x| pixels[y * width + x])
+                            });
+                        options.block_spec(block_pixels)
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let layout = &options.layout;
+        TileMap::from_pattern(&pattern, layout.tile_width, layout.tile_height, layout.gap)
+    }
+
+    // Parse a level written in the text format described at `parse_level`, returning
+    // its header along with the built tile map
+    pub fn from_level_str(source: &str) -> Result<(LevelHeader, TileMap), LevelParseError> {
+        let (header, pattern) = parse_level(source)?;
+        let layout = &header.layout;
+        let tile_map =
+            TileMap::from_pattern(&pattern, layout.tile_width, layout.tile_height, layout.gap);
+        Ok((header, tile_map))
+    }
+
+    // Size and place every tile so the whole grid fits in `playfield` as `layout` asks.
+    // Tiles are shrunk when the pattern wouldn't fit at its preferred size
+    pub fn apply_layout(&mut self, layout: &TileLayout, playfield: Rect) {
+        let cols = self.cols.max(1) as f32;
+        let rows = self.rows.max(1) as f32;
+        let gap = layout.gap;
+
+        let area_height = (playfield.h * layout.max_height - layout.top_margin).max(0.0);
+        // Largest tile that still fits every column and row, gaps included
+        let fit = Vec2::new(
+            (playfield.w / cols - gap).max(1.0),
+            (area_height / rows - gap).max(1.0),
+        );
+
+        let mut size = Vec2::new(layout.tile_width, layout.tile_height);
+        if layout.fit_width {
+            size = if layout.keep_aspect {
+                size * (fit.x / size.x)
+            } else {
+                Vec2::new(fit.x, size.y)
+            };
+        }
+        if size.x > fit.x {
+            size = if layout.keep_aspect {
+                size * (fit.x / size.x)
+            } else {
+                Vec2::new(fit.x, size.y)
+            };
+        }
+        if size.y > fit.y {
+            size = if layout.keep_aspect {
+                size * (fit.y / size.y)
+            } else {
+                Vec2::new(size.x, fit.y)
+            };
+        }
+
+        let pitch = size + Vec2::splat(gap);
+        let left = if layout.center {
+            playfield.x + (playfield.w - cols * pitch.x) / 2.0
+        } else {
+            playfield.x
+        };
+
+        self.tile_size = size;
+        self.gap = gap;
+        self.origin = Vec2::new(left, playfield.y + layout.top_margin) + Vec2::splat(gap / 2.0);
+
+        for (row, tiles) in self.tiles.iter_mut().enumerate() {
+            for (col, tile) in tiles.iter_mut().enumerate() {
+                tile.position = self.origin + Vec2::new(col as f32, row as f32) * pitch;
+                tile.size = size;
+            }
+        }
+    }
+}
+
+// Where and how big a map's tiles are laid out in the playfield
+#[derive(Debug, Clone, PartialEq)]
+pub struct TileLayout {
+    // Preferred tile size, used as-is whenever the pattern fits at that size
+    pub tile_width: f32,
+    pub tile_height: f32,
+    pub gap: f32,
+    // Stretch the tiles so the grid spans the full playfield width
+    pub fit_width: bool,
+    // Centre the grid horizontally, otherwise it starts at the left edge
+    pub center: bool,
+    // Space between the top of the playfield and the first row
+    pub top_margin: f32,
+    // Keep tiles in the preferred width/height ratio when they are resized
+    pub keep_aspect: bool,
+    // Fraction of the playfield height the tiles (and top margin) can take up
+    pub max_height: f32,
+}
+
+impl Default for TileLayout {
+    fn default() -> Self {
+        TileLayout {
+            tile_width: 75.0,
+            tile_height: 25.0,
+            gap: 5.0,
+            fit_width: false,
+            center: true,
+            top_margin: 0.0,
+            keep_aspect: true,
+            max_height: 2.0 / 3.0,
+        }
+    }
+}
+
+// Everything a level file says about the level apart from its tiles
+#[derive(Debug, Clone, PartialEq)]
+pub struct LevelHeader {
+    pub name: String,
+    pub layout: TileLayout,
+    pub ball_speed: f32,
+}
+
+// How `TileMap::from_image` turns pixels into tiles
+#[derive(Debug, Clone)]
+pub struct ImageLevelOptions {
+    pub layout: TileLayout,
+    // Size of the square block of pixels that makes up one tile, 1 is a tile per pixel.
+    // A block takes its most common colour and is a gap if it is mostly transparent
+    pub downsample: u32,
+    // Pixel colours that become a special kind of tile, any other colour is a normal one
+    pub kinds: Vec<(Color, TileKind)>,
+    // Pixels with less alpha than this (0-255) count as transparent
+    pub alpha_threshold: u8,
+}
+
+impl Default for ImageLevelOptions {
+    fn default() -> Self {
+        ImageLevelOptions {
+            layout: TileLayout::default(),
+            downsample: 1,
+            kinds: vec![],
+            alpha_threshold: 128,
+        }
+    }
+}
+
+impl ImageLevelOptions {
+    // The tile for one block of RGBA pixels, None for a gap
+    fn block_spec(&self, pixels: impl Iterator<Item = [u8; 4]>) -> Option<TileSpec> {
+        let mut total = 0;
+        let mut counts: Vec<([u8; 3], usize)> = vec![];
+        for [r, g, b, a] in pixels {
+            total += 1;
+            if a < self.alpha_threshold {
+                continue;
+            }
+            match counts.iter_mut().find(|(rgb, _)| *rgb == [r, g, b]) {
+                Some((_, count)) => *count += 1,
+                None => counts.push(([r, g, b], 1)),
+            }
+        }
+
+        let opaque: usize = counts.iter().map(|(_, count)| count).sum();
+        if opaque * 2 <= total {
+            return None;
+        }
+        let (rgb, _) = counts.into_iter().max_by_key(|&(_, count)| count)?;
+
+        let kind = self
+            .kinds
+            .iter()
+            .find(|(color, _)| rgb_bytes(*color) == rgb)
+            .map_or(TileKind::Normal, |&(_, kind)| kind);
+        Some(TileSpec::new(pixel_color(rgb), kind))
+    }
+}
+
+// A colour as the 8-bit RGB it is saved with in an image
+fn rgb_bytes(color: Color) -> [u8; 3] {
+    let [r, g, b, _]: [u8; 4] = color.into();
+    [r, g, b]
+}
+
+// An opaque pixel colour, snapped to the named colour it was saved from if there is
+// one, so tiles drawn in e.g. DARKGREEN are exactly DARKGREEN (and score like it)
+fn pixel_color(rgb: [u8; 3]) -> Color {
+    COLOR_NAMES
+        .iter()
+        .map(|&(_, color)| color)
+        .find(|&color| color.a == 1.0 && rgb_bytes(color) == rgb)
+        .unwrap_or(Color::from_rgba(rgb[0], rgb[1], rgb[2], 255))
+}
+
+// Where a level file went wrong, lines and columns count from 1
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LevelParseError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl LevelParseError {
+    fn new(line: usize, column: usize, message: impl Into<String>) -> Self {
+        LevelParseError {
+            line,
+            column,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for LevelParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "line {}, column {}: {}",
+            self.line, self.column, self.message
+        )
+    }
+}
+
+impl std::error::Error for LevelParseError {}
+
+// Colour names a level legend can use, anything else has to be written as #rrggbb
+const COLOR_NAMES: [(&str, Color); 25] = [
+    ("LIGHTGRAY", LIGHTGRAY),
+    ("GRAY", GRAY),
+    ("DARKGRAY", DARKGRAY),
+    ("YELLOW", YELLOW),
+    ("GOLD", GOLD),
+    ("ORANGE", ORANGE),
+    ("PINK", PINK),
+    ("RED", RED),
+    ("MAROON", MAROON),
+    ("GREEN", GREEN),
+    ("LIME", LIME),
+    ("DARKGREEN", DARKGREEN),
+    ("SKYBLUE", SKYBLUE),
+    ("BLUE", BLUE),
+    ("DARKBLUE", DARKBLUE),
+    ("PURPLE", PURPLE),
+    ("VIOLET", VIOLET),
+    ("DARKPURPLE", DARKPURPLE),
+    ("BEIGE", BEIGE),
+    ("BROWN", BROWN),
+    ("DARKBROWN", DARKBROWN),
+    ("WHITE", WHITE),
+    ("BLACK", BLACK),
+    ("MAGENTA", MAGENTA),
+    ("BLANK", BLANK),
+];
+
+// Grid character that is always an empty cell
+const EMPTY_CELL: char = '.';
+
+// Which part of a level file is being read
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum LevelSection {
+    Header,
+    Legend,
+    Grid,
+}
+
+// Column (from 1) where `part`, a slice of `line`, starts
+fn column_of(line: &str, part: &str) -> usize {
+    let offset = part.as_ptr() as usize - line.as_ptr() as usize;
+    line[..offset].chars().count() + 1
+}
+
+// A level file is a header, a legend and a grid, in that order:
+//
+//     // Lines starting with // are comments (outside the grid)
+//     name: Creeper
+//     tile_size: 75 25
+//     gap: 5
+//     ball_speed: 270
+//     top_margin: 20
+//
+//     legend:
+//     G = DARKGREEN
+//     B = BLACK hits 2
+//     X = #ff8800 explosive
+//
+//     grid:
+//     .GG..GG.
+//     GBBGGBBG
+//
+// `tile_size`, `gap` and the optional `fit_width`, `center`, `keep_aspect` (true or
+// false), `top_margin` and `max_height` header fields set the `TileLayout`.
+// Legend entries map one character to a colour (a name or #rrggbb) and optionally a
+// kind: `hits N`, `indestructible`, `explosive`, `powerup` or `portal N`. In the grid
+// `.` is an empty cell, every row has to be the same width
+fn parse_level(source: &str) -> Result<(LevelHeader, TilePattern), LevelParseError> {
+    let mut name = None;
+    let mut tile_size = None;
+    let mut gap = None;
+    let mut ball_speed = None;
+    let mut layout = TileLayout::default();
+    let mut legend: Vec<(char, TileSpec)> = vec![];
+    let mut pattern: TilePattern = vec![];
+
+    let mut section = LevelSection::Header;
+    let mut last_line = 0;
+
+    for (index, line) in source.lines().enumerate() {
+        let line_number = index + 1;
+        last_line = line_number;
+        let text = line.trim();
+
+        if section != LevelSection::Grid {
+            if text.is_empty() || text.starts_with("//") {
+                continue;
+            }
+            if text == "legend:" {
+                section = LevelSection::Legend;
+                continue;
+            }
+            if text == "grid:" {
+                section = LevelSection::Grid;
+                continue;
+            }
+        }
+
+        match section {
+            LevelSection::Header => {
+                let error = |part: &str, message: String| {
+                    LevelParseError::new(line_number, column_of(line, part), message)
+                };
+                let Some((key, value)) = text.split_once(':') else {
+                    return Err(error(text, "expected `key: value`".to_string()));
+                };
+                let (key, value) = (key.trim(), value.trim());
+
+                let number = |part: &str| {
+                    part.parse::<f32>()
+                        .ok()
+                        .filter(|number| number.is_finite() && *number >= 0.0)
+                        .ok_or_else(|| error(part, format!("`{part}` is not a valid number")))
+                };
+                // Sizes and speeds of zero would leave the ball stuck or the grid empty
+                let positive = |part: &str| {
+                    let number = number(part)?;
+                    if number > 0.0 {
+                        Ok(number)
+                    } else {
+                        Err(error(part, format!("`{part}` has to be greater than 0")))
+                    }
+                };
+                let boolean = |part: &str| match part {
+                    "true" | "yes" => Ok(true),
+                    "false" | "no" => Ok(false),
+                    _ => Err(error(part, format!("`{part}` should be true or false"))),
+                };
+
+                match key {
+                    "name" => name = Some(value.to_string()),
+                    "tile_size" => {
+                        let parts: Vec<&str> = value.split_whitespace().collect();
+                        let [width, height] = parts[..] else {
+                            return Err(error(
+                                value,
+                                "expected `tile_size: WIDTH HEIGHT`".to_string(),
+                            ));
+                        };
+                        tile_size = Some((positive(width)?, positive(height)?));
+                    }
+                    "gap" => gap = Some(number(value)?),
+                    "ball_speed" => ball_speed = Some(positive(value)?),
+                    "fit_width" => layout.fit_width = boolean(value)?,
+                    "center" => layout.center = boolean(value)?,
+                    "keep_aspect" => layout.keep_aspect = boolean(value)?,
+                    "top_margin" => layout.top_margin = number(value)?,
+                    "max_height" => layout.max_height = positive(value)?.min(1.0),
+                    _ => return Err(error(key, format!("unknown header field `{key}`"))),
+                }
+            }
+            LevelSection::Legend => {
+                let entry = parse_legend_entry(line, line_number)?;
+                if entry.0 == EMPTY_CELL {
+                    return Err(LevelParseError::new(
+                        line_number,
+                        column_of(line, text),
+                        format!("`{EMPTY_CELL}` is always an empty cell"),
+                    ));
+                }
+                if legend.iter().any(|(symbol, _)| *symbol == entry.0) {
+                    return Err(LevelParseError::new(
+                        line_number,
+                        column_of(line, text),
+                        format!("`{}` is already in the legend", entry.0),
+                    ));
+                }
+                legend.push(entry);
+            }
+            LevelSection::Grid => {
+                let row = line.trim_end();
+                if row.is_empty() {
+                    continue;
+                }
+
+                let mut cells = vec![];
+                for (column, symbol) in row.chars().enumerate() {
+                    if symbol == EMPTY_CELL {
+                        cells.push(None);
+                        continue;
+                    }
+                    let Some(&(_, spec)) = legend.iter().find(|(entry, _)| *entry == symbol) else {
+                        return Err(LevelParseError::new(
+                            line_number,
+                            column + 1,
+                            format!("`{symbol}` is not in the legend"),
+                        ));
+                    };
+                    cells.push(Some(spec));
+                }
+
+                if let Some(first) = pattern.first()
+                    && first.len() != cells.len()
+                {
+                    return Err(LevelParseError::new(
+                        line_number,
+                        cells.len().min(first.len()) + 1,
+                        format!(
+                            "row is {} tiles wide, the first row is {}",
+                            cells.len(),
+                            first.len()
+                        ),
+                    ));
+                }
+                pattern.push(cells);
+            }
+        }
+    }
+
+    // Anything missing is reported at the end of the file
+    let missing = |what: &str| LevelParseError::new(last_line.max(1), 1, format!("missing {what}"));
+    if pattern.is_empty() {
+        return Err(missing("`grid:` with at least one row"));
+    }
+    (layout.tile_width, layout.tile_height) = tile_size.ok_or_else(|| missing("`tile_size`"))?;
+    layout.gap = gap.ok_or_else(|| missing("`gap`"))?;
+    let header = LevelHeader {
+        name: name.ok_or_else(|| missing("`name`"))?,
+        layout,
+        ball_speed: ball_speed.ok_or_else(|| missing("`ball_speed`"))?,
+    };
+
+    Ok((header, pattern))
+}
+
+// One legend line: `SYMBOL = COLOUR [KIND]`
+fn parse_legend_entry(line: &str, line_number: usize) -> Result<(char, TileSpec), LevelParseError> {
+    let error = |part: &str, message: String| {
+        LevelParseError::new(line_number, column_of(line, part), message)
+    };
+
+    let text = line.trim();
+    let Some((symbol, definition)) = text.split_once('=') else {
+        return Err(error(text, "expected `SYMBOL = COLOUR [KIND]`".to_string()));
+    };
+    let symbol = symbol.trim();
+    let mut symbol_chars = symbol.chars();
+    let (Some(character), None) = (symbol_chars.next(), symbol_chars.next()) else {
+        return Err(error(
+            symbol,
+            format!("`{symbol}` should be a single character"),
+        ));
+    };
+
+    let words: Vec<&str> = definition.split_whitespace().collect();
+    let Some((&color_word, kind_words)) = words.split_first() else {
+        return Err(error(definition, "missing a colour".to_string()));
+    };
+    let color = parse_color(color_word)
+        .ok_or_else(|| error(color_word, format!("unknown colour `{color_word}`")))?;
+
+    let count = |word: &str| {
+        word.parse::<u32>()
+            .ok()
+            .filter(|count| *count > 0)
+            .ok_or_else(|| error(word, format!("`{word}` is not a positive whole number")))
+    };
+
+    let kind = match kind_words {
+        [] => TileKind::Normal,
+        ["hits", hits] => TileKind::MultiHit(count(hits)?),
+        ["indestructible"] => TileKind::Indestructible,
+        ["explosive"] => TileKind::Explosive,
+        ["powerup"] => TileKind::PowerUpCarrier,
+        ["portal", channel] => {
+            let channel = count(channel)?;
+            TileKind::Portal(u8::try_from(channel).map_err(|_| {
+                error(
+                    kind_words[1],
+                    format!("portal channel `{channel}` is over 255"),
+                )
+            })?)
+        }
+        [word, ..] => {
+            return Err(error(
+                word,
+                "expected `hits N`, `indestructible`, `explosive`, `powerup` or `portal N`"
+                    .to_string(),
+            ));
+        }
+    };
+
+    Ok((character, TileSpec::new(color, kind)))
+}
+
+// A colour name from `COLOR_NAMES` or a #rrggbb hex code
+fn parse_color(word: &str) -> Option<Color> {
+    if let Some(hex) = word.strip_prefix('#') {
+        if hex.len() != 6 || !hex.chars().all(|digit| digit.is_ascii_hexdigit()) {
+            return None;
+        }
+        return u32::from_str_radix(hex, 16).ok().map(Color::from_hex);
+    }
+
+    COLOR_NAMES
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(word))
+        .map(|&(_, color)| color)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn arena() -> Arena {
+        Arena::new(Rect::new(0.0, 0.0, 1280.0, 720.0))
+    }
+
+    fn laid_out(source: &str) -> TileMap {
+        let (header, mut tile_map) = TileMap::from_level_str(source).unwrap();
+        tile_map.apply_layout(&header.layout, arena().bounds);
+        tile_map
+    }
+
+    // Earliest tile hit found by sweeping every tile in the map, no grid lookup
+    fn sweep_every_tile(
+        tile_map: &TileMap,
+        start: Vec2,
+        motion: Vec2,
+        radius: f32,
+    ) -> Option<(SweepHit, Vec<(usize, usize)>)> {
+        let mut hits = vec![];
+        for (row, tiles) in tile_map.tiles.iter().enumerate() {
+            for (col, tile) in tiles.iter().enumerate() {
+                if tile.destroyed || !tile.is_solid {
+                    continue;
+                }
+                if let Some(hit) = sweep_circle_rect(start, motion, radius, tile.get_rect()) {
+                    hits.push((hit, row, col));
+                }
+            }
+        }
+
+        let sweep_hits: Vec<SweepHit> = hits.iter().map(|&(hit, _, _)| hit).collect();
+        let combined = combine_hits(&sweep_hits)?;
+        let tiles = hits
+            .iter()
+            .filter(|(hit, _, _)| is_simultaneous(hit.time, combined.time))
+            .map(|&(_, row, col)| (row, col))
+            .collect();
+        Some((combined, tiles))
+    }
+
+    #[test]
+    fn sweep_ball_matches_sweeping_every_tile() {
+        let (header, mut tile_map) =
+            TileMap::from_level_str(include_str!("../../assets/levels/stripes.txt")).unwrap();
+        // An off-centre playfield, so the grid doesn't start at the origin
+        let playfield = Rect::new(37.0, 23.0, 1100.0, 650.0);
+        tile_map.apply_layout(&header.layout, playfield);
+        assert!(tile_map.origin.x > 37.0 && tile_map.origin.y > 23.0);
+
+        // Small xorshift so the sweeps are random but the same on every run
+        let mut state = 0x2545_f491_u32;
+        let mut random = |low: f32, high: f32| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            low + (high - low) * (state as f32 / u32::MAX as f32)
+        };
+
+        for _ in 0..20_000 {
+            let start = Vec2::new(random(-50.0, 1200.0), random(-50.0, 500.0));
+            let motion = Vec2::new(random(-200.0, 200.0), random(-200.0, 200.0));
+            let radius = random(2.0, 30.0);
+
+            let grid = tile_map
+                .sweep_ball(start, motion, radius)
+                .map(|sweep| (sweep.hit, sweep.tiles));
+            let every_tile = sweep_every_tile(&tile_map, start, motion, radius);
+
+            match (grid, every_tile) {
+                (None, None) => {}
+                (Some((grid_hit, grid_tiles)), Some((hit, tiles))) => {
+                    assert_eq!(grid_hit.time, hit.time, "{start} {motion} {radius}");
+                    assert_eq!(grid_hit.normal, hit.normal, "{start} {motion} {radius}");
+                    assert_eq!(grid_tiles, tiles, "{start} {motion} {radius}");
+                }
+                (grid, every_tile) => panic!(
+                    "{start} {motion} {radius}: grid found {:?}, every tile found {:?}",
+                    grid.map(|(_, tiles)| tiles),
+                    every_tile.map(|(_, tiles)| tiles)
+                ),
+            }
+        }
+    }
+
+    fn explosive_map(grid: &str) -> TileMap {
+        laid_out(&format!(
+            "{HEADER}legend:\nX = ORANGE explosive\nR = RED\nB = MAROON hits 2\n# = GRAY indestructible\n1 = PURPLE portal 1\ngrid:\n{grid}"
+        ))
+    }
+
+    fn hit_cells(hits: &[TileHit]) -> Vec<(usize, usize)> {
+        let mut cells: Vec<_> = hits.iter().map(|hit| (hit.row, hit.col)).collect();
+        cells.sort();
+        cells
+    }
+
+    #[test]
+    fn explosive_damages_exactly_its_eight_neighbours() {
+        let mut tile_map = explosive_map("RRRRR\nRBRRR\nRRXRR\nRRRRR\nRRRRR\n");
+
+        let hits = tile_map.hit_tile(2, 2);
+
+        let mut expected = vec![];
+        for row in 1..=3 {
+            for col in 1..=3 {
+                expected.push((row, col));
+            }
+        }
+        assert_eq!(hit_cells(&hits), expected);
+        for (row, tiles) in tile_map.tiles.iter().enumerate() {
+            for (col, tile) in tiles.iter().enumerate() {
+                let in_blast = expected.contains(&(row, col));
+                assert_eq!(
+                    tile.hit_points < tile.max_hit_points,
+                    in_blast,
+                    "({row}, {col})"
+                );
+            }
+        }
+        // The two hit tile only loses one of its hit points
+        assert!(!tile_map.tiles[1][1].destroyed);
+        assert_eq!(tile_map.tiles[1][1].hit_points, 1);
+    }
+
+    #[test]
+    fn explosive_leaves_indestructible_and_portal_tiles_alone() {
+        let mut tile_map = explosive_map("#1R\nRX.\nR#1\n");
+
+        let hits = tile_map.hit_tile(1, 1);
+
+        assert_eq!(hit_cells(&hits), vec![(0, 2), (1, 0), (1, 1), (2, 0)]);
+        for (row, col) in [(0, 0), (0, 1), (2, 1), (2, 2)] {
+            let tile = &tile_map.tiles[row][col];
+            assert!(!tile.destroyed, "({row}, {col})");
+            assert_eq!(tile.hit_points, tile.max_hit_points, "({row}, {col})");
+        }
+    }
+
+    #[test]
+    fn neighbouring_explosive_goes_off_after_the_chain_delay() {
+        let mut tile_map = explosive_map("XXR\n");
+
+        let hits = tile_map.hit_tile(0, 0);
+        assert_eq!(hit_cells(&hits), vec![(0, 0), (0, 1)]);
+        assert!(!tile_map.tiles[0][2].destroyed);
+
+        assert!(tile_map.update(CHAIN_DELAY * 0.5).is_empty());
+        assert!(!tile_map.tiles[0][2].destroyed);
+
+        let hits = tile_map.update(CHAIN_DELAY * 0.6);
+        assert_eq!(hit_cells(&hits), vec![(0, 2)]);
+        assert!(tile_map.tiles[0][2].destroyed);
+    }
+
+    #[test]
+    fn chained_explosions_report_every_tile_once() {
+        let mut tile_map = explosive_map("XXXR\nXXXR\nRXRR\n");
+
+        let mut hits = tile_map.hit_tile(0, 0);
+        for _ in 0..20 {
+            hits.extend(tile_map.update(CHAIN_DELAY));
+        }
+
+        let cells = hit_cells(&hits);
+        let mut unique = cells.clone();
+        unique.dedup();
+        assert_eq!(cells, unique);
+        // Every tile is within reach of one explosive or another
+        assert_eq!(cells.len(), 12);
+        assert!(tile_map.is_cleared());
+    }
+
+    const HEADER: &str = "name: Test\ntile_size: 40 20\ngap: 2\nball_speed: 300\n";
+
+    fn parse_error(source: &str) -> (usize, usize, String) {
+        let error = TileMap::from_level_str(source).err().unwrap();
+        (error.line, error.column, error.message)
+    }
+
+    #[test]
+    fn level_str_parses_header_legend_and_grid() {
+        let source = format!(
+            "// comment\n{HEADER}top_margin: 10\n\nlegend:\nR = RED\nB = #0000ff hits 3\n# = GRAY indestructible\n\ngrid:\nR.B\n#R.\n"
+        );
+        let (header, tile_map) = TileMap::from_level_str(&source).unwrap();
+
+        assert_eq!(header.name, "Test");
+        assert_eq!(header.ball_speed, 300.0);
+        assert_eq!(
+            (header.layout.tile_width, header.layout.tile_height),
+            (40.0, 20.0)
+        );
+        assert_eq!(header.layout.gap, 2.0);
+        assert_eq!(header.layout.top_margin, 10.0);
+        assert_eq!((tile_map.rows, tile_map.cols), (2, 3));
+
+        let tile = |row: usize, col: usize| &tile_map.tiles[row][col];
+        assert_eq!(tile(0, 0).color, RED);
+        assert!(tile(0, 1).destroyed && !tile(0, 1).is_solid);
+        assert_eq!(tile(0, 2).color, Color::from_hex(0x0000ff));
+        assert_eq!(tile(0, 2).kind, TileKind::MultiHit(3));
+        assert_eq!(tile(1, 0).kind, TileKind::Indestructible);
+    }
+
+    #[test]
+    fn unknown_legend_character_is_reported_where_it_is_used() {
+        let source = format!("{HEADER}legend:\nR = RED\ngrid:\nRR\nRX\n");
+        let (line, column, message) = parse_error(&source);
+        assert_eq!((line, column), (9, 2));
+        assert!(message.contains("`X`"), "{message}");
+    }
+
+    #[test]
+    fn ragged_row_is_reported_where_it_ends() {
+        let source = format!("{HEADER}legend:\nR = RED\ngrid:\nRRR\nRR\n");
+        let (line, column, message) = parse_error(&source);
+        assert_eq!((line, column), (9, 3));
+        assert!(message.contains("2 tiles wide"), "{message}");
+    }
+
+    #[test]
+    fn unknown_colour_is_reported_at_the_colour() {
+        let source = format!("{HEADER}legend:\nR = REDD explosive\ngrid:\nR\n");
+        let (line, column, message) = parse_error(&source);
+        assert_eq!((line, column), (6, 5));
+        assert!(message.contains("`REDD`"), "{message}");
+    }
+
+    #[test]
+    fn multi_character_symbol_is_reported_at_the_symbol() {
+        let source = format!("{HEADER}legend:\n  RR = RED\ngrid:\nR\n");
+        let (line, column, message) = parse_error(&source);
+        assert_eq!((line, column), (6, 3));
+        assert!(message.contains("single character"), "{message}");
+    }
+
+    #[test]
+    fn missing_header_field_is_reported_at_the_end() {
+        let source = "name: Test\ntile_size: 40 20\ngap: 2\nlegend:\nR = RED\ngrid:\nR\n";
+        let (line, column, message) = parse_error(source);
+        assert_eq!((line, column), (7, 1));
+        assert!(message.contains("`ball_speed`"), "{message}");
+    }
+
+    #[test]
+    fn zero_sizes_and_speeds_are_rejected_at_the_value() {
+        let body = "legend:\nR = RED\ngrid:\nR\n";
+
+        let source = format!("name: Test\ntile_size: 0 0\ngap: 0\nball_speed: 300\n{body}");
+        let (line, column, _) = parse_error(&source);
+        assert_eq!((line, column), (2, 12));
+
+        let source = format!("name: Test\ntile_size: 40 20\ngap: 0\nball_speed: 0\n{body}");
+        let (line, column, _) = parse_error(&source);
+        assert_eq!((line, column), (4, 13));
+    }
+
+    #[test]
+    fn portal_exit_stays_inside_the_arena() {
+        let tile_map = laid_out(include_str!("../../assets/levels/vault.txt"));
+        let (row, left, right) = (5, 0, tile_map.cols - 1);
+        assert_eq!(tile_map.tiles[row][left].kind, TileKind::Portal(1));
+        let radius = 15.0;
+
+        // Coming out of the right portal heading right would leave the arena
+        let up_right = Vec2::new(0.95, -0.3) * 370.0;
+        assert_eq!(
+            tile_map.portal_exit(row, left, up_right, radius, &arena()),
+            None
+        );
+
+        // Heading down there is room below the partner portal
+        let down = Vec2::new(0.0, 370.0);
+        let exit = tile_map
+            .portal_exit(row, right, down, radius, &arena())
+            .unwrap();
+        let partner = tile_map.tiles[row][left].get_rect();
+        assert!(exit.y - radius > partner.bottom());
+        assert!(arena().bounds.contains(exit));
+    }
+
+    fn grid(rows: usize, cols: usize) -> TileMap {
+        TileMap::from_pattern(&vec![vec![Some(RED); cols]; rows], 1.0, 1.0, 0.0)
+    }
+
+    fn assert_close(actual: Vec2, expected: Vec2) {
+        assert!(actual.abs_diff_eq(expected, 1e-3), "{actual} != {expected}");
+    }
+
+    #[test]
+    fn sixteen_columns_with_gaps_fit_the_playfield_width() {
+        let mut tile_map = grid(2, 16);
+        let layout = TileLayout {
+            tile_width: 80.0,
+            tile_height: 20.0,
+            gap: 5.0,
+            ..Default::default()
+        };
+        let playfield = Rect::new(0.0, 0.0, 1280.0, 720.0);
+
+        tile_map.apply_layout(&layout, playfield);
+
+        // 80px tiles plus their gaps don't fit, so they shrink to 75px, keeping their shape
+        assert_close(tile_map.tile_size, Vec2::new(75.0, 18.75));
+        let first = tile_map.tiles[0][0].get_rect();
+        let last = tile_map.tiles[0][15].get_rect();
+        assert!(first.left() >= playfield.left());
+        assert!(last.right() <= playfield.right());
+        assert_eq!(
+            first.left() - playfield.left(),
+            playfield.right() - last.right()
+        );
+    }
+
+    #[test]
+    fn fit_width_stretches_tiles_across_the_playfield() {
+        let playfield = Rect::new(0.0, 0.0, 1280.0, 720.0);
+        let mut layout = TileLayout {
+            tile_width: 40.0,
+            tile_height: 20.0,
+            gap: 2.0,
+            fit_width: true,
+            ..Default::default()
+        };
+
+        let mut tile_map = grid(1, 4);
+        tile_map.apply_layout(&layout, playfield);
+        assert_close(tile_map.tile_size, Vec2::new(318.0, 159.0));
+
+        layout.keep_aspect = false;
+        tile_map.apply_layout(&layout, playfield);
+        assert_close(tile_map.tile_size, Vec2::new(318.0, 20.0));
+    }
+
+    #[test]
+    fn uncentred_grid_starts_at_the_playfield_edge() {
+        let mut tile_map = grid(2, 3);
+        let layout = TileLayout {
+            tile_width: 40.0,
+            tile_height: 20.0,
+            gap: 4.0,
+            center: false,
+            top_margin: 30.0,
+            ..Default::default()
+        };
+
+        tile_map.apply_layout(&layout, Rect::new(100.0, 50.0, 1000.0, 600.0));
+
+        assert_close(tile_map.origin, Vec2::new(102.0, 82.0));
+        assert_close(tile_map.tiles[1][2].position, Vec2::new(190.0, 106.0));
+    }
+
+    #[test]
+    fn tall_grid_shrinks_to_fit_under_max_height() {
+        let mut tile_map = grid(10, 2);
+        let layout = TileLayout {
+            tile_width: 80.0,
+            tile_height: 20.0,
+            gap: 5.0,
+            top_margin: 20.0,
+            max_height: 0.25,
+            ..Default::default()
+        };
+        let playfield = Rect::new(0.0, 0.0, 1280.0, 720.0);
+
+        tile_map.apply_layout(&layout, playfield);
+
+        // 180px allowed, less the margin, leaves 16px per row gaps included
+        assert_close(tile_map.tile_size, Vec2::new(44.0, 11.0));
+        assert_eq!(tile_map.origin.y, 22.5);
+        let bottom = tile_map.tiles[9][0].get_rect().bottom();
+        assert!(bottom <= playfield.h * layout.max_height, "{bottom}");
+    }
+
+    fn pixel(color: Color, alpha: u8) -> [u8; 4] {
+        let [r, g, b] = rgb_bytes(color);
+        [r, g, b, alpha]
+    }
+
+    // An image from rows of RGBA pixels
+    fn image(rows: &[&[[u8; 4]]]) -> Image {
+        Image {
+            bytes: rows
+                .iter()
+                .flat_map(|row| row.iter().flatten())
+                .copied()
+                .collect(),
+            width: rows[0].len() as u16,
+            height: rows.len() as u16,
+        }
+    }
+
+    #[test]
+    fn image_block_takes_its_most_common_colour() {
+        let (red, blue) = (pixel(RED, 255), pixel(BLUE, 255));
+        let image = image(&[&[red, blue, blue, blue], &[red, red, blue, red]]);
+        let options = ImageLevelOptions {
+            downsample: 2,
+            ..Default::default()
+        };
+
+        let tile_map = TileMap::from_image(&image, &options);
+
+        assert_eq!((tile_map.rows, tile_map.cols), (1, 2));
+        assert_eq!(tile_map.tiles[0][0].color, RED);
+        assert_eq!(tile_map.tiles[0][1].color, BLUE);
+    }
+
+    #[test]
+    fn image_block_at_least_half_transparent_is_a_gap() {
+        let (red, clear) = (pixel(RED, 255), pixel(RED, 0));
+        let image = image(&[&[red, red, red, red], &[clear, clear, red, clear]]);
+        let options = ImageLevelOptions {
+            downsample: 2,
+            ..Default::default()
+        };
+
+        let tile_map = TileMap::from_image(&image, &options);
+
+        let half = &tile_map.tiles[0][0];
+        assert!(half.destroyed && !half.is_solid);
+        let mostly_opaque = &tile_map.tiles[0][1];
+        assert!(!mostly_opaque.destroyed && mostly_opaque.is_solid);
+    }
+
+    #[test]
+    fn alpha_threshold_decides_which_pixels_are_transparent() {
+        let image = image(&[&[pixel(RED, 100), pixel(RED, 200)]]);
+
+        let tile_map = TileMap::from_image(&image, &ImageLevelOptions::default());
+        assert!(tile_map.tiles[0][0].destroyed);
+        assert!(!tile_map.tiles[0][1].destroyed);
+
+        let options = ImageLevelOptions {
+            alpha_threshold: 50,
+            ..Default::default()
+        };
+        let tile_map = TileMap::from_image(&image, &options);
+        assert!(!tile_map.tiles[0][0].destroyed);
+        assert!(!tile_map.tiles[0][1].destroyed);
+    }
+
+    #[test]
+    fn image_colours_map_to_tile_kinds() {
+        let image = image(&[&[pixel(ORANGE, 255), pixel(GRAY, 255), pixel(RED, 255)]]);
+        let options = ImageLevelOptions {
+            kinds: vec![
+                (ORANGE, TileKind::Explosive),
+                (GRAY, TileKind::Indestructible),
+            ],
+            ..Default::default()
+        };
+
+        let tile_map = TileMap::from_image(&image, &options);
+
+        let kinds: Vec<_> = tile_map.tiles[0].iter().map(|tile| tile.kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                TileKind::Explosive,
+                TileKind::Indestructible,
+                TileKind::Normal
+            ]
+        );
+    }
+
+    #[test]
+    fn image_pixels_snap_to_named_colours() {
+        let [r, g, b] = rgb_bytes(DARKGREEN);
+        let image = image(&[&[pixel(DARKGREEN, 255), [r, g, b.wrapping_add(1), 255]]]);
+
+        let tile_map = TileMap::from_image(&image, &ImageLevelOptions::default());
+
+        assert_eq!(tile_map.tiles[0][0].color, DARKGREEN);
+        // A colour with no name is kept as it was saved
+        assert_eq!(
+            tile_map.tiles[0][1].color,
+            Color::from_rgba(r, g, b.wrapping_add(1), 255)
+        );
+    }
 }
@@ -0,0 +1 @@
+pub mod viewport_class;
\ No newline at end of file
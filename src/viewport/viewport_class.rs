@@ -0,0 +1,63 @@
+use macroquad::prelude::*;
+
+use crate::{SCREEN_HEIGHT, SCREEN_WIDTH};
+
+// Where the fixed-size virtual playfield sits in the window. It is scaled to fit while
+// keeping its aspect ratio and centred, letterbox bars fill the rest of the window
+pub struct Viewport {
+    // Window pixels per playfield unit
+    pub scale: f32,
+    // Top-left corner of the playfield in the window
+    pub offset: Vec2,
+}
+
+impl Viewport {
+    // Fit the playfield to the window at its current size
+    pub fn fit_window() -> Self {
+        let window = Vec2::new(screen_width(), screen_height());
+        let scale = (window.x / SCREEN_WIDTH).min(window.y / SCREEN_HEIGHT);
+        let offset = (window - Viewport::size() * scale) / 2.0;
+
+        Viewport { scale, offset }
+    }
+
+    fn size() -> Vec2 {
+        Vec2::new(SCREEN_WIDTH, SCREEN_HEIGHT)
+    }
+
+    // The whole playfield in its own coordinates
+    pub fn world_rect() -> Rect {
+        Rect::new(0.0, 0.0, SCREEN_WIDTH, SCREEN_HEIGHT)
+    }
+
+    // Camera that draws playfield coordinates into the letterboxed part of the window
+    pub fn camera(&self) -> Camera2D {
+        // Viewports are in physical pixels, counted from the bottom of the window
+        let dpi = screen_dpi_scale();
+        let size = Viewport::size() * self.scale;
+        let bottom = screen_height() - self.offset.y - size.y;
+
+        Camera2D {
+            viewport: Some((
+                (self.offset.x * dpi) as i32,
+                (bottom * dpi) as i32,
+                (size.x * dpi) as i32,
+                (size.y * dpi) as i32,
+            )),
+            ..Camera2D::from_display_rect(Viewport::world_rect())
+        }
+    }
+
+    // Map a point in the window (like the mouse) into playfield coordinates
+    pub fn screen_to_world(&self, point: Vec2) -> Vec2 {
+        (point - self.offset) / self.scale
+    }
+
+    // The mouse in playfield coordinates, None while it is over the letterbox bars
+    pub fn mouse_position(&self) -> Option<Vec2> {
+        let position = self.screen_to_world(mouse_position().into());
+        Viewport::world_rect()
+            .contains(position)
+            .then_some(position)
+    }
+}